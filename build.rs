@@ -54,6 +54,23 @@ enum AttributeValue {
     VideoBandwidth,
 }
 
+impl AttributeValue {
+    fn name(&self) -> &'static str {
+        match self {
+            AttributeValue::None => "none",
+            AttributeValue::Range { .. } => "range",
+            AttributeValue::Discrete { .. } => "discrete",
+            AttributeValue::CommandAndString => "cmdstr",
+            AttributeValue::Delay => "delay",
+            AttributeValue::Unbounded => "unbounded",
+            AttributeValue::TypeSlope => "typeslope",
+            AttributeValue::FreqencyAndGain => "freqgain",
+            AttributeValue::Date => "date",
+            AttributeValue::VideoBandwidth => "videoBandwidth",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum AttributeCommand {
@@ -117,7 +134,61 @@ enum AttributeIndex {
     Port,
 }
 
+impl AttributeCommand {
+    /// Command string sent to device, `None` for attributes without command
+    fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            AttributeCommand::Get => "get",
+            AttributeCommand::Set => "set",
+            AttributeCommand::Increment => "increment",
+            AttributeCommand::Decrement => "decrement",
+            AttributeCommand::Toggle => "toggle",
+            AttributeCommand::Subscribe => "subscribe",
+            AttributeCommand::Unsubscribe => "unsubscribe",
+            AttributeCommand::Empty => return None,
+            AttributeCommand::Dial => "dial",
+            AttributeCommand::SpeedDial => "speedDial",
+            AttributeCommand::Redial => "redial",
+            AttributeCommand::End => "end",
+            AttributeCommand::Flash => "flash",
+            AttributeCommand::Send => "send",
+            AttributeCommand::Dtmf => "dtmf",
+            AttributeCommand::Answer => "answer",
+            AttributeCommand::Lconf => "lconf",
+            AttributeCommand::Resume => "resume",
+            AttributeCommand::Hold => "hold",
+            AttributeCommand::OffHook => "offHook",
+            AttributeCommand::OnHook => "onHook",
+        })
+    }
+}
+
 impl AttributeIndex {
+    /// Human readable name of index, `None` for attributes without index
+    fn name(&self) -> Option<&'static str> {
+        Some(match self {
+            AttributeIndex::Channel => "channel",
+            AttributeIndex::AVChannel => "AV channel",
+            AttributeIndex::AuxiliaryAudioChannel => "auxiliary audio channel",
+            AttributeIndex::Band => "band",
+            AttributeIndex::Filter => "filter",
+            AttributeIndex::Command => "command",
+            AttributeIndex::InputGroup => "input group",
+            AttributeIndex::None => return None,
+            AttributeIndex::Line => "line",
+            AttributeIndex::SpeedDialEntry => "speed dial entry",
+            AttributeIndex::CallAppearance => "call appearance",
+            AttributeIndex::CallAppearanceIndex => "call appearance index",
+            AttributeIndex::Source => "source",
+            AttributeIndex::Output => "output",
+            AttributeIndex::Input => "input",
+            AttributeIndex::Room => "room",
+            AttributeIndex::Wall => "wall",
+            AttributeIndex::Hostname => "hostname",
+            AttributeIndex::Port => "port",
+        })
+    }
+
     fn to_parameter_name(&self) -> &'static str {
         match self {
             AttributeIndex::Channel => "channel_index",
@@ -125,7 +196,7 @@ impl AttributeIndex {
             AttributeIndex::AuxiliaryAudioChannel => "auxiliary_audio_channel_index",
             AttributeIndex::Band => "band",
             AttributeIndex::Filter => "filter",
-            AttributeIndex::Command => "command",
+            AttributeIndex::Command => "command_index",
             AttributeIndex::InputGroup => "input_group",
             AttributeIndex::None => {
                 panic!("Attempt to create parameter name out of None attribute index")
@@ -184,6 +255,44 @@ fn to_struct_name(value: &str, parent: &str) -> String {
         })
 }
 
+/// Source of the `BLOCKS` static describing every block, sorted by name
+fn blocks_meta(blocks: &TesiraBlocks) -> String {
+    let mut block_names = blocks.keys().collect::<Vec<_>>();
+    block_names.sort();
+
+    let mut meta = String::from(
+        "/// Every block type of the catalog, sorted by name\npub static BLOCKS: &[BlockMeta] = &[\n",
+    );
+    for block_name in block_names {
+        let block = &blocks[block_name];
+        meta.push_str(&format!(
+            "\tBlockMeta {{\n\t\tname: {block_name:?},\n\t\tgroup: {:?},\n\t\tattributes: &[\n",
+            block.group
+        ));
+        for attribute in &block.attributes {
+            meta.push_str(&format!(
+                "\t\t\tAttributeMeta {{ name: {:?}, description: {:?}, commands: &{:?}, indexes: &{:?}, value_type: {:?} }},\n",
+                attribute.name,
+                attribute.description.trim(),
+                attribute
+                    .commands
+                    .iter()
+                    .filter_map(AttributeCommand::name)
+                    .collect::<Vec<_>>(),
+                attribute
+                    .indexes
+                    .iter()
+                    .filter_map(AttributeIndex::name)
+                    .collect::<Vec<_>>(),
+                attribute.value.name()
+            ));
+        }
+        meta.push_str("\t\t],\n\t},\n");
+    }
+    meta.push_str("];\n");
+    meta
+}
+
 fn main() {
     let generated_dir = Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("generated");
     fs::create_dir_all(&generated_dir).unwrap();
@@ -195,9 +304,26 @@ fn main() {
         .open(generated_dir.join("tesira-blocks.rs"))
         .unwrap();
 
+    let mut values_f = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(generated_dir.join("tesira-values.rs"))
+        .unwrap();
+
+    let mut meta_f = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(generated_dir.join("tesira-blocks-meta.rs"))
+        .unwrap();
+
     let blocks: TesiraBlocks = serde_json::from_str(include_str!("tesira-blocks.json")).unwrap();
 
+    meta_f.write_all(blocks_meta(&blocks).as_bytes()).unwrap();
+
     let mut scope = Scope::new();
+    let mut values_scope = Scope::new();
     let mut builder_impl = Impl::new("CommandBuilder");
 
     for (block_name, block) in blocks.into_iter() {
@@ -210,12 +336,12 @@ fn main() {
         ));
 
         let instance_tag_var = if block_name == "Session Services" {
-            "\"SESSION\""
+            "InstanceTag::from(\"SESSION\")"
         } else if block_name == "Device Services" {
-            "\"DEVICE\""
+            "InstanceTag::from(\"DEVICE\")"
         } else {
             block_builder.tuple_field("InstanceTag");
-            "self.0"
+            "self.0.clone()"
         };
 
         let mut block_builder_impl = Impl::new(builder_type.clone());
@@ -245,6 +371,8 @@ fn main() {
             }
         }
 
+        // Bounds of range attributes, exposed so hand-written builders reuse catalog data
+        let mut range_consts: Vec<String> = Vec::new();
         let mut discrete_types: HashMap<Vec<String>, String> = HashMap::new();
         {
             let mut bool_vec = vec!["false".to_owned(), "true".to_owned()];
@@ -254,6 +382,7 @@ fn main() {
 
         for attribute in block.attributes.iter() {
             for command in attribute.commands.iter() {
+                let mut closing = "}";
                 let new_fn: Vec<(Function, Vec<(&'static str, String)>)> = match command {
                     AttributeCommand::Get => {
                         let extra_args: Vec<(&'static str, String)> = Vec::new();
@@ -269,12 +398,24 @@ fn main() {
                     }
                     AttributeCommand::Set => {
                         let mut extra_args: Vec<(&'static str, String)> = Vec::new();
+                        // Bounded ranges are checked at runtime and return a Result
+                        let range_checked = matches!(
+                            attribute.value,
+                            AttributeValue::Range {
+                                min: Some(_),
+                                max: Some(_)
+                            }
+                        );
                         let mut new_fn = Function::new(to_fn_name("set_", &attribute.name));
                         new_fn
                             .vis("pub")
                             .ret("Command<'static>")
                             .doc(format!("Set {}", attribute.description))
-                            .line("Command {")
+                            .line(if range_checked {
+                                "Ok(Command {"
+                            } else {
+                                "Command {"
+                            })
                             .line("\tcommand: COMMAND_SET,");
 
                         let mut extra_fn = Vec::new();
@@ -302,7 +443,12 @@ fn main() {
                                                 attribute.description, block_name
                                             ))
                                             .vis("pub")
-                                            .allow("missing_docs");
+                                            .allow("missing_docs")
+                                            .derive("Debug")
+                                            .derive("Clone")
+                                            .derive("Copy")
+                                            .derive("PartialEq")
+                                            .derive("Eq");
                                         let mut new_enum_impl = Impl::new(enum_name.clone());
                                         new_enum_impl.impl_trait("IntoTTP");
                                         let convert_fn = new_enum_impl
@@ -311,18 +457,49 @@ fn main() {
                                             .arg_self()
                                             .line("match self {");
 
+                                        let mut display_impl = Impl::new(enum_name.clone());
+                                        display_impl.impl_trait("Display");
+                                        let display_fn = display_impl
+                                            .new_fn("fmt")
+                                            .arg_ref_self()
+                                            .arg("f", "&mut Formatter<'_>")
+                                            .ret("fmt::Result")
+                                            .line("f.write_str(match self {");
+
+                                        let mut from_str_impl = Impl::new(enum_name.clone());
+                                        from_str_impl
+                                            .impl_trait("FromStr")
+                                            .associate_type("Err", "UnknownValueError");
+                                        let from_str_fn = from_str_impl
+                                            .new_fn("from_str")
+                                            .arg("s", "&str")
+                                            .ret("Result<Self, Self::Err>")
+                                            .line("match s {");
+
                                         for variant in values {
                                             let variant_name = to_struct_name(variant, &enum_name);
                                             convert_fn.line(format!(
                                                 "\tSelf::{variant_name} => \"{variant}\".to_owned(),"
                                             ));
+                                            display_fn.line(format!(
+                                                "\tSelf::{variant_name} => \"{variant}\","
+                                            ));
+                                            from_str_fn.line(format!(
+                                                "\t\"{variant}\" => Ok(Self::{variant_name}),"
+                                            ));
                                             new_enum.new_variant(variant_name);
                                         }
 
                                         convert_fn.line("}");
-
-                                        scope.push_enum(new_enum);
-                                        scope.push_impl(new_enum_impl);
+                                        display_fn.line("})");
+                                        from_str_fn
+                                            .line("\t_ => Err(UnknownValueError(s.to_owned())),")
+                                            .line("}");
+
+                                        values_scope.push_enum(new_enum);
+                                        values_scope.push_impl(new_enum_impl);
+                                        values_scope.push_impl(display_impl);
+                                        values_scope.push_impl(from_str_impl);
                                         enum_name
                                     });
 
@@ -331,12 +508,23 @@ fn main() {
 
                                 // TODO other descrete value
                             }
-                            AttributeValue::Range {
-                                min: _min,
-                                max: _max,
-                            } => {
+                            AttributeValue::Range { min, max } => {
                                 extra_args.push(("value", "f64".to_owned()));
-                                new_fn.line("\tvalues: vec![value.into_ttp()],");
+                                if let (Some(min), Some(max)) = (min, max) {
+                                    let const_name = to_fn_name("", &attribute.name)
+                                        .trim_start_matches("r#")
+                                        .to_uppercase();
+                                    range_consts.push(format!(
+                                        "    /// Minimum value of {}\n    pub const {const_name}_MIN: f64 = {min:?};\n\n    /// Maximum value of {}\n    pub const {const_name}_MAX: f64 = {max:?};\n",
+                                        attribute.description, attribute.description
+                                    ));
+                                    new_fn
+                                        .ret("Result<Command<'static>, OutOfRangeError>")
+                                        .line(format!("\tvalues: vec![OutOfRangeError::check(value, {min:?}, {max:?})?.into_ttp()],"));
+                                    closing = "})";
+                                } else {
+                                    new_fn.line("\tvalues: vec![value.into_ttp()],");
+                                }
                             }
                             AttributeValue::Unbounded => {
                                 extra_args.push(("value", "impl IntoTTP".to_owned()));
@@ -352,15 +540,26 @@ fn main() {
                                 new_fn.line("\tvalues: vec![format!(\"{{\\\"type\\\":{} \\\"slope\\\":{}}}\", filter_type.into_ttp(), filter_slope.into_ttp())],");
                             }
                             AttributeValue::FreqencyAndGain => {
-                                extra_args.push(("freqency", "f64".to_owned()));
+                                extra_args.push(("frequency", "f64".to_owned()));
                                 extra_args.push(("gain", "f64".to_owned()));
-                                new_fn.line("\tvalues: vec![format!(\"{{\\\"frequency\\\":{} \\\"gain\\\":{}}}\", freqency.into_ttp(), gain.into_ttp())],");
+                                new_fn.line(
+                                    "\tvalues: vec![FreqGain { frequency, gain }.into_ttp()],",
+                                );
                             }
                             AttributeValue::Date => {
-                                extra_args.push(("value", "NaiveDateTime".to_owned()));
-                                new_fn.line("\tvalues: vec![value.into_ttp()],");
+                                extra_args.push(("value", "impl Into<FormattedDate>".to_owned()));
+                                new_fn.line("\tvalues: vec![value.into().into_ttp()],");
+                            }
+                            AttributeValue::CommandAndString => {
+                                // Sets both the command ID (label) and the command string
+                                extra_args.push(("command", "impl Into<String>".to_owned()));
+                                extra_args.push(("string", "impl Into<String>".to_owned()));
+                                new_fn.doc(format!(
+                                    "Set {}\n\nTargets attribute `{}`, `command` is the command ID and `string` the command string",
+                                    attribute.description, attribute.name
+                                ));
+                                new_fn.line("\tvalues: vec![format!(\"{{\\\"label\\\":{} \\\"command\\\":{}}}\", Value::String(command.into()), Value::String(string.into()))],");
                             }
-                            AttributeValue::CommandAndString => continue, //TODO
                             AttributeValue::VideoBandwidth => continue, // Video Bandwidth not supported fo rnow
                         }
 
@@ -390,7 +589,7 @@ fn main() {
                             .doc(format!("Subscribe to {} value update", attribute.description))
                             .line("Command {")
                             .line("\tcommand: COMMAND_SUBSCRIBE,")
-                            .line("\tvalues: vec![subscription_label.into().into_ttp(), min_rate.as_millis().into_ttp()],");
+                            .line("\tvalues: vec![subscription_label.into().into_ttp(), min_rate.into_ttp()],");
 
                         vec![
                             (
@@ -424,13 +623,114 @@ fn main() {
                             vec![("subscription_label", "impl Into<String>".to_owned())],
                         )]
                     }
+                    AttributeCommand::Increment | AttributeCommand::Decrement => {
+                        let (prefix, command_const, verb) =
+                            if matches!(command, AttributeCommand::Increment) {
+                                ("increment_", "COMMAND_INCREMENT", "Increment")
+                            } else {
+                                ("decrement_", "COMMAND_DECREMENT", "Decrement")
+                            };
+                        let amount_type = match &attribute.value {
+                            // Discrete values that can be stepped are numeric
+                            AttributeValue::Range { .. } | AttributeValue::Discrete { .. } => "f64",
+                            AttributeValue::None | AttributeValue::Unbounded => "impl IntoTTP",
+                            _ => continue,
+                        };
+
+                        let mut new_fn = Function::new(to_fn_name(prefix, &attribute.name));
+                        new_fn
+                            .vis("pub")
+                            .ret("Command<'static>")
+                            .doc(format!("{verb} {}", attribute.description))
+                            .line("Command {")
+                            .line(format!("\tcommand: {command_const},"))
+                            .line("\tvalues: vec![amount.into_ttp()],");
+                        vec![(new_fn, vec![("amount", amount_type.to_owned())])]
+                    }
+                    AttributeCommand::Toggle => {
+                        let extra_args: Vec<(&'static str, String)> = Vec::new();
+                        let mut new_fn = Function::new(to_fn_name("toggle_", &attribute.name));
+                        new_fn
+                            .vis("pub")
+                            .ret("Command<'static>")
+                            .doc(format!("Toggle {}", attribute.description))
+                            .line("Command {")
+                            .line("\tcommand: COMMAND_TOGGLE,")
+                            .line("\tvalues: Vec::new(),");
+                        vec![(new_fn, extra_args)]
+                    }
+                    AttributeCommand::Dial => {
+                        let mut new_fn = Function::new("dial");
+                        new_fn
+                            .vis("pub")
+                            .ret("Command<'static>")
+                            .doc(&attribute.description)
+                            .line("Command {")
+                            .line("\tcommand: COMMAND_DIAL,")
+                            .line("\tvalues: vec![Value::String(number.into()).into_ttp()],");
+                        vec![(new_fn, vec![("number", "impl Into<String>".to_owned())])]
+                    }
+                    AttributeCommand::Dtmf => {
+                        let mut new_fn = Function::new("dtmf");
+                        new_fn
+                            .vis("pub")
+                            .ret("Command<'static>")
+                            .doc(&attribute.description)
+                            .line("Command {")
+                            .line("\tcommand: COMMAND_DTMF,")
+                            .line("\tvalues: vec![Value::String(digits.into()).into_ttp()],");
+                        vec![(new_fn, vec![("digits", "impl Into<String>".to_owned())])]
+                    }
+                    AttributeCommand::SpeedDial => {
+                        let mut new_fn = Function::new("speed_dial");
+                        new_fn
+                            .vis("pub")
+                            .ret("Command<'static>")
+                            .doc(&attribute.description)
+                            .line("Command {")
+                            .line("\tcommand: COMMAND_SPEED_DIAL,")
+                            .line("\tvalues: vec![entry.into_ttp()],");
+                        vec![(new_fn, vec![("entry", "IndexValue".to_owned())])]
+                    }
+                    AttributeCommand::Redial
+                    | AttributeCommand::End
+                    | AttributeCommand::Flash
+                    | AttributeCommand::Send
+                    | AttributeCommand::Answer
+                    | AttributeCommand::Lconf
+                    | AttributeCommand::Resume
+                    | AttributeCommand::Hold
+                    | AttributeCommand::OffHook
+                    | AttributeCommand::OnHook => {
+                        let (fn_name, command_const) = match command {
+                            AttributeCommand::Redial => ("redial", "COMMAND_REDIAL"),
+                            AttributeCommand::End => ("end", "COMMAND_END"),
+                            AttributeCommand::Flash => ("flash", "COMMAND_FLASH"),
+                            AttributeCommand::Send => ("send", "COMMAND_SEND"),
+                            AttributeCommand::Answer => ("answer", "COMMAND_ANSWER"),
+                            AttributeCommand::Lconf => ("lconf", "COMMAND_LCONF"),
+                            AttributeCommand::Resume => ("resume", "COMMAND_RESUME"),
+                            AttributeCommand::Hold => ("hold", "COMMAND_HOLD"),
+                            AttributeCommand::OffHook => ("off_hook", "COMMAND_OFF_HOOK"),
+                            _ => ("on_hook", "COMMAND_ON_HOOK"),
+                        };
+                        let mut new_fn = Function::new(fn_name);
+                        new_fn
+                            .vis("pub")
+                            .ret("Command<'static>")
+                            .doc(&attribute.description)
+                            .line("Command {")
+                            .line(format!("\tcommand: {command_const},"))
+                            .line("\tvalues: Vec::new(),");
+                        vec![(new_fn, Vec::new())]
+                    }
                     _ => continue, // TODO
                 };
 
                 for (mut new_fn, extra_args) in new_fn.into_iter() {
                     new_fn.line(format!("\tattribute: \"{}\",", attribute.name));
                     new_fn.arg_ref_self();
-                    new_fn.line(format!("\tinstance_tag: {instance_tag_var}.to_owned(),"));
+                    new_fn.line(format!("\tinstance_tag: {instance_tag_var},"));
 
                     let mut indexes_param = Vec::new();
                     for index in attribute
@@ -444,7 +744,7 @@ fn main() {
                     }
                     new_fn.line(format!("\tindexes: vec![{}],", indexes_param.join(", ")));
 
-                    new_fn.line("}");
+                    new_fn.line(closing);
 
                     for arg in extra_args {
                         new_fn.arg(arg.0, arg.1);
@@ -457,11 +757,20 @@ fn main() {
 
         scope.push_struct(block_builder);
         scope.push_impl(block_builder_impl);
+        if !range_consts.is_empty() {
+            scope.raw(format!(
+                "impl {builder_type} {{\n{}}}",
+                range_consts.join("\n")
+            ));
+        }
     }
 
     scope.push_impl(builder_impl);
 
     f.write_all(scope.to_string().as_bytes()).unwrap();
+    values_f
+        .write_all(values_scope.to_string().as_bytes())
+        .unwrap();
 
     println!("cargo::rerun-if-changed=tesira-blocks.json");
     println!("cargo::rerun-if-changed=build.rs");
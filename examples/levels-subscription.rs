@@ -21,7 +21,7 @@ fn main() {
     let username = inquire("Username [admin]").unwrap_or_else(|| "admin".to_owned());
     let password = inquire("Password").expect("Password is mendatory");
 
-    let mut session = TesiraSession::new_from_ssh(&hostname, &username, &password)
+    let mut session = TesiraSession::new_from_ssh_host(&hostname, &username, &password)
         .expect("Failed to open Tesira session");
 
     println!("Session opened");
@@ -39,8 +39,9 @@ fn main() {
 
     println!("Subscribed to AudioMeter1 level 1");
 
-    loop {
-        let token = session.recv_token().unwrap();
-        println!("Value received: {:?}", token.value)
+    for token in session.tokens() {
+        println!("Value received: {:?}", token.unwrap().value)
     }
+
+    println!("Session ended");
 }
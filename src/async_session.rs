@@ -0,0 +1,629 @@
+//! Asynchronous Tesira Text Protocol session based on Tokio
+//!
+//! Mirrors [TesiraSession](crate::TesiraSession) with non-blocking I/O,
+//! commands and responses are shared with the blocking session.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(feature = "ssh")]
+use std::io;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+#[cfg(feature = "ssh")]
+use tokio::io::{DuplexStream, ReadBuf, ReadHalf, WriteHalf};
+
+#[cfg(feature = "ssh")]
+use crate::{SshCredentials, open_ssh_shell};
+
+use crate::{
+    Error, SessionOptions, check_response_len,
+    proto::{
+        Command, IntoTTP, OkResponse, PublishToken, Response, Value,
+        commands::{COMMAND_GET, COMMAND_SUBSCRIBE, COMMAND_UNSUBSCRIBE},
+    },
+    subscription_label, trim_carriage_return,
+};
+
+/// Follows an active Tesira Text Protocol session over asynchronous streams
+pub struct AsyncTesiraSession<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> {
+    read_stream: BufReader<R>,
+    write_stream: W,
+    read_buffer: String,
+    pending_token: VecDeque<PublishToken>,
+    multiline_errors: bool,
+    options: SessionOptions,
+    subscriptions: HashMap<String, Subscription>,
+}
+
+/// Command lines sent again for a subscription on reconnection
+struct Subscription {
+    subscribe_line: String,
+    get_line: String,
+}
+
+#[cfg(feature = "ssh")]
+impl AsyncTesiraSession<ReadHalf<DuplexStream>, WriteHalf<DuplexStream>> {
+    /// Connect to tesira device over SSH
+    ///
+    /// The blocking [ssh2] client is driven by a dedicated thread, bridged to the session
+    /// through an in-memory stream. The thread ends when the session is dropped or device
+    /// closes the channel. Must be called from within a Tokio runtime.
+    pub async fn new_from_ssh(
+        hostname: impl std::net::ToSocketAddrs + Send + 'static,
+        username: &str,
+        password: &str,
+    ) -> Result<Self, Error> {
+        let username = username.to_owned();
+        let password = password.to_owned();
+        let channel = tokio::task::spawn_blocking(move || {
+            let credentials = SshCredentials {
+                addrs: hostname.to_socket_addrs()?.collect(),
+                username,
+                password,
+            };
+            let session = credentials.connect()?;
+            let channel = open_ssh_shell(&session)?;
+            session.set_blocking(false);
+            Ok::<_, Error>(channel)
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        let (stream, bridge) = tokio::io::duplex(SSH_BRIDGE_BUFFER_LEN);
+        std::thread::spawn(move || bridge_ssh_channel(channel, bridge));
+        let (read_stream, write_stream) = tokio::io::split(stream);
+        Self::new_from_stream(read_stream, write_stream).await
+    }
+}
+
+#[cfg(feature = "ssh")]
+const SSH_BRIDGE_BUFFER_LEN: usize = 16 * 1024;
+
+/// Copy data both ways between a non-blocking SSH channel and a session stream
+///
+/// Both sides are polled without blocking, sleeping a little when neither made progress.
+#[cfg(feature = "ssh")]
+fn bridge_ssh_channel(mut channel: ssh2::Channel, mut stream: DuplexStream) {
+    use std::{
+        io::{Read, Write},
+        pin::Pin,
+        task::{Context, Poll, Waker},
+        time::Duration,
+    };
+
+    let mut context = Context::from_waker(Waker::noop());
+    let mut buffer = [0; SSH_BRIDGE_BUFFER_LEN];
+    let mut to_session = Vec::new();
+    let mut to_device = Vec::new();
+    loop {
+        let mut idle = true;
+
+        if to_session.is_empty() {
+            match channel.read(&mut buffer) {
+                Ok(0) if channel.eof() => return,
+                Ok(n) => to_session.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return,
+            }
+        }
+        if !to_session.is_empty() {
+            match Pin::new(&mut stream).poll_write(&mut context, &to_session) {
+                Poll::Ready(Ok(n)) => {
+                    to_session.drain(..n);
+                    idle = false;
+                }
+                Poll::Ready(Err(_)) => return,
+                Poll::Pending => {}
+            }
+        }
+
+        if to_device.is_empty() {
+            let mut read_buf = ReadBuf::new(&mut buffer);
+            match Pin::new(&mut stream).poll_read(&mut context, &mut read_buf) {
+                // Session was dropped
+                Poll::Ready(Ok(())) if read_buf.filled().is_empty() => {
+                    let _ = channel.close();
+                    return;
+                }
+                Poll::Ready(Ok(())) => to_device.extend_from_slice(read_buf.filled()),
+                Poll::Ready(Err(_)) => return,
+                Poll::Pending => {}
+            }
+        }
+        if !to_device.is_empty() {
+            match channel.write(&to_device) {
+                Ok(n) => {
+                    to_device.drain(..n);
+                    idle = false;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return,
+            }
+        }
+
+        if idle {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> AsyncTesiraSession<R, W> {
+    /// Create a new session from arbitrary asynchronous read and write stream
+    pub async fn new_from_stream(read_stream: R, write_stream: W) -> Result<Self, Error> {
+        Self::new_from_stream_with_options(read_stream, write_stream, SessionOptions::default())
+            .await
+    }
+
+    /// Create a new session from arbitrary asynchronous read and write stream with custom options
+    pub async fn new_from_stream_with_options(
+        read_stream: R,
+        write_stream: W,
+        options: SessionOptions,
+    ) -> Result<Self, Error> {
+        let mut new_self = Self {
+            read_stream: BufReader::new(read_stream),
+            write_stream,
+            read_buffer: String::new(),
+            pending_token: VecDeque::new(),
+            multiline_errors: false,
+            options,
+            subscriptions: HashMap::new(),
+        };
+        new_self.wait_banner().await?;
+        Ok(new_self)
+    }
+
+    async fn wait_banner(&mut self) -> Result<(), Error> {
+        let options = &self.options;
+        let mut banner_buffer = String::new();
+        let mut line_count = 0;
+        let mut byte_count = 0;
+        loop {
+            if line_count >= options.banner_max_lines || byte_count >= options.banner_max_bytes {
+                return Err(Error::BannerNotFound);
+            }
+            // Wait for welcome line, skipping blank lines
+            banner_buffer.clear();
+            let byte_red = (&mut self.read_stream)
+                .take((options.banner_max_bytes - byte_count) as u64)
+                .read_line(&mut banner_buffer)
+                .await?;
+            if byte_red == 0 {
+                return Err(Error::UnexpectedEnd);
+            }
+            line_count += 1;
+            byte_count += byte_red;
+            if banner_buffer.trim().is_empty() {
+                continue;
+            }
+            if banner_buffer.starts_with("Welcome") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Accumulate indented lines following an error response into its message
+    ///
+    /// See [TesiraSession::set_multiline_errors](crate::TesiraSession::set_multiline_errors)
+    pub fn set_multiline_errors(&mut self, enabled: bool) {
+        self.multiline_errors = enabled;
+    }
+
+    /// Get all available aliases
+    pub async fn get_aliases(&mut self) -> Result<HashSet<String>, Error> {
+        let response = self
+            .send_command(Command::builder().session().aliases())
+            .await?;
+        if let OkResponse::WithList(l) = response {
+            Ok(l.into_iter()
+                .filter_map(|it| match it {
+                    Value::String(v) => Some(v),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>())
+        } else {
+            Err(Error::UnexpectedResponse(
+                Response::Ok(response),
+                "a response with a list of aliases".to_owned(),
+            ))
+        }
+    }
+
+    /// Send direct command and await for a response from device
+    pub async fn send_command<'a, 'b: 'a>(
+        &'a mut self,
+        cmd: impl Into<Command<'b>>,
+    ) -> Result<OkResponse, Error> {
+        let command: Command = cmd.into();
+        self.send_line(command.into_ttp()).await
+    }
+
+    async fn send_line(&mut self, line: String) -> Result<OkResponse, Error> {
+        self.write_stream
+            .write_all(format!("{line}\n").as_bytes())
+            .await?;
+        self.write_stream.flush().await?;
+        loop {
+            let response = self.recv_response().await?;
+            match response {
+                Response::Err(response) => {
+                    return Err(Error::CommandFailed {
+                        command: line,
+                        response,
+                    });
+                }
+                Response::Ok(res) => return Ok(res),
+                Response::PublishToken(t) => self.pending_token.push_front(t),
+            }
+        }
+    }
+
+    /// Send a subscribe command and register it to be sent again by [Self::reconnect]
+    ///
+    /// Publish tokens are received with [Self::recv_token]. The command must be a "subscribe"
+    /// command with a subscription label as first value, such as built by [Command::new_subscribe].
+    pub async fn subscribe<'a, 'b: 'a>(
+        &'a mut self,
+        cmd: impl Into<Command<'b>>,
+    ) -> Result<(), Error> {
+        let command: Command = cmd.into();
+        let label = subscription_label(&command, COMMAND_SUBSCRIBE)
+            .ok_or_else(|| Error::InvalidSubscription(command.clone().into_ttp()))?;
+        // Current value is read with the same block, attribute and indexes
+        let get_line = Command {
+            command: COMMAND_GET,
+            values: Vec::new(),
+            ..command.clone()
+        }
+        .into_ttp();
+
+        let subscribe_line = command.into_ttp();
+        self.send_line(subscribe_line.clone()).await?;
+        self.subscriptions.insert(
+            label,
+            Subscription {
+                subscribe_line,
+                get_line,
+            },
+        );
+        Ok(())
+    }
+
+    /// Send an unsubscribe command and forget its subscription
+    ///
+    /// The command must be an "unsubscribe" command with a subscription label as first value,
+    /// such as built by [Command::new_unsubscribe].
+    pub async fn unsubscribe<'a, 'b: 'a>(
+        &'a mut self,
+        cmd: impl Into<Command<'b>>,
+    ) -> Result<(), Error> {
+        let command: Command = cmd.into();
+        let label = subscription_label(&command, COMMAND_UNSUBSCRIBE)
+            .ok_or_else(|| Error::InvalidSubscription(command.clone().into_ttp()))?;
+
+        self.send_command(command).await?;
+        self.subscriptions.remove(&label);
+        Ok(())
+    }
+
+    /// Continue session over new streams and restore subscriptions
+    ///
+    /// Streams are opened by the caller, such as after a dropped connection. The welcome banner
+    /// is awaited again with the options the session was opened with, then every subscribe
+    /// command registered with [Self::subscribe] is sent again with the same label.
+    /// Current value of each subscription is read and queued as a publish token for
+    /// [Self::recv_token], so updates missed while disconnected are not lost.
+    /// A subscription whose value can't be read is restored without a queued value.
+    pub async fn reconnect(&mut self, read_stream: R, write_stream: W) -> Result<(), Error> {
+        self.read_stream = BufReader::new(read_stream);
+        self.write_stream = write_stream;
+        self.read_buffer.clear();
+        self.pending_token.clear();
+        self.wait_banner().await?;
+
+        let subscriptions = self
+            .subscriptions
+            .iter()
+            .map(|(label, it)| {
+                (
+                    label.clone(),
+                    it.subscribe_line.clone(),
+                    it.get_line.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (label, subscribe_line, get_line) in subscriptions {
+            self.send_line(subscribe_line).await?;
+            // Attributes that can't be read are subscribed without seeding a value
+            match self.send_line(get_line).await {
+                Ok(OkResponse::WithValue(value)) => {
+                    self.pending_token.push_front(PublishToken { label, value })
+                }
+                Ok(_) | Err(Error::CommandFailed { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn recv_response(&mut self) -> Result<Response, Error> {
+        // Buffer is kept between calls so steady state reads do not allocate
+        self.read_buffer.clear();
+        loop {
+            // Ignore empty lines
+            let remaining = self
+                .options
+                .max_response_len
+                .saturating_sub(self.read_buffer.len());
+            let byte_red = (&mut self.read_stream)
+                .take(remaining as u64)
+                .read_line(&mut self.read_buffer)
+                .await?;
+            check_response_len(&mut self.read_buffer, self.options.max_response_len)?;
+            if byte_red == 0 {
+                return Err(Error::UnexpectedEnd);
+            }
+            trim_carriage_return(&mut self.read_buffer);
+
+            let trim_buf = self.read_buffer.trim();
+            if !trim_buf.is_empty()
+                && (&trim_buf[0..1] == "-" || &trim_buf[0..1] == "+" || &trim_buf[0..1] == "!")
+            {
+                if self.multiline_errors && &trim_buf[0..1] == "-" {
+                    while matches!(self.read_stream.buffer().first(), Some(b' ' | b'\t')) {
+                        let remaining = self
+                            .options
+                            .max_response_len
+                            .saturating_sub(self.read_buffer.len());
+                        (&mut self.read_stream)
+                            .take(remaining as u64)
+                            .read_line(&mut self.read_buffer)
+                            .await?;
+                        check_response_len(&mut self.read_buffer, self.options.max_response_len)?;
+                        trim_carriage_return(&mut self.read_buffer);
+                    }
+                }
+                return Ok(Response::parse_ttp(&self.read_buffer)?);
+            } else {
+                self.read_buffer.clear();
+            }
+        }
+    }
+
+    /// Await for a publish token to come
+    pub async fn recv_token(&mut self) -> Result<PublishToken, Error> {
+        if let Some(pending_token) = self.pending_token.pop_back() {
+            return Ok(pending_token);
+        }
+
+        let response = self.recv_response().await?;
+        match response {
+            Response::PublishToken(t) => Ok(t),
+            r @ (Response::Err(_) | Response::Ok(_)) => {
+                Err(Error::UnexpectedResponse(r, "a publish token".to_owned()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use pretty_assertions::assert_eq;
+
+    use super::AsyncTesiraSession;
+    use crate::{
+        Error,
+        proto::{Command, ErrResponse, OkResponse, PublishToken, Value},
+    };
+
+    fn welcome_banner() -> Vec<u8> {
+        "Welcome to the Tesira Text Protocol Server...\n\n"
+            .as_bytes()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_end_before_banner() {
+        let session =
+            AsyncTesiraSession::new_from_stream(Cursor::new(Vec::new()), Vec::new()).await;
+        assert!(matches!(session, Err(Error::UnexpectedEnd)));
+    }
+
+    #[tokio::test]
+    async fn should_flush_command_before_awaiting_response() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK\n".as_bytes());
+
+        let mut session = AsyncTesiraSession::new_from_stream(
+            Cursor::new(input),
+            tokio::io::BufWriter::new(Vec::new()),
+        )
+        .await
+        .unwrap();
+        session
+            .send_command(Command::new_set("Level3", "mute", [1], true))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            session.write_stream.get_ref(),
+            "Level3 set mute 1 true\n".as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_handle_valid_get_command() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 get level 2\n".as_bytes()); // Should also handle echo
+        input.extend_from_slice("+OK \"value\":0.000000\n".as_bytes());
+
+        let mut session = AsyncTesiraSession::new_from_stream(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+        let response = session
+            .send_command(Command::new_get("Level3", "level", [2]))
+            .await
+            .unwrap();
+
+        assert_eq!(session.write_stream, "Level3 get level 2\n".as_bytes());
+        assert_eq!(response, OkResponse::WithValue(Value::Number(0.0)));
+    }
+
+    #[tokio::test]
+    async fn should_handle_failed_operation() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 set mute 3 true\n".as_bytes()); // Should also handle echo
+        input.extend_from_slice(
+            "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}\n"
+                .as_bytes(),
+        );
+
+        let mut session = AsyncTesiraSession::new_from_stream(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+        let response = session
+            .send_command(Command::new_set("Level3", "mute", [3], true))
+            .await;
+
+        if let Err(Error::CommandFailed { response: e, .. }) = response {
+            assert_eq!(
+                e,
+                ErrResponse::new(
+                    "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
+                )
+            )
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+    }
+
+    #[tokio::test]
+    async fn should_handle_subscription() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("LogicMeter1 subscribe state 1 Subscription0\n".as_bytes());
+        input
+            .extend_from_slice("! \"publishToken\":\"Subscription0\" \"value\":false\n".as_bytes());
+        input.extend_from_slice("+OK\n".as_bytes());
+        input.extend_from_slice("! \"publishToken\":\"Subscription0\" \"value\":true\n".as_bytes());
+
+        let mut session = AsyncTesiraSession::new_from_stream(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+        session
+            .send_command(Command::new_subscribe(
+                "LogicMeter1",
+                "state",
+                [1],
+                "Subscription0",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            session.recv_token().await.unwrap(),
+            PublishToken {
+                label: "Subscription0".to_owned(),
+                value: Value::Boolean(false)
+            }
+        );
+        assert_eq!(
+            session.recv_token().await.unwrap(),
+            PublishToken {
+                label: "Subscription0".to_owned(),
+                value: Value::Boolean(true)
+            }
+        );
+        assert!(matches!(
+            session.recv_token().await,
+            Err(Error::UnexpectedEnd)
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_restore_subscriptions_on_reconnect() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 subscribe level 1 MyLevel\n".as_bytes());
+        input.extend_from_slice("! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n".as_bytes());
+        input.extend_from_slice("+OK\n".as_bytes());
+
+        let mut session = AsyncTesiraSession::new_from_stream(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+        session
+            .subscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+            .await
+            .unwrap();
+        assert_eq!(
+            session.recv_token().await.unwrap().value,
+            Value::Number(-10.0)
+        );
+        // Connection dropped by device
+        assert!(matches!(
+            session.recv_token().await,
+            Err(Error::UnexpectedEnd)
+        ));
+
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 subscribe level 1 MyLevel\n+OK\n".as_bytes());
+        input.extend_from_slice("Level3 get level 1\n+OK \"value\":-5.000000\n".as_bytes());
+        input.extend_from_slice("! \"publishToken\":\"MyLevel\" \"value\":-4.000000\n".as_bytes());
+        session
+            .reconnect(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            session.write_stream,
+            "Level3 subscribe level 1 MyLevel\nLevel3 get level 1\n".as_bytes()
+        );
+        assert_eq!(
+            session.recv_token().await.unwrap(),
+            PublishToken {
+                label: "MyLevel".to_owned(),
+                value: Value::Number(-5.0)
+            }
+        );
+        assert_eq!(
+            session.recv_token().await.unwrap().value,
+            Value::Number(-4.0)
+        );
+
+        session
+            .unsubscribe(Command::new_unsubscribe("Level3", "level", [1], "MyLevel"))
+            .await
+            .unwrap_err();
+        assert!(session.subscriptions.contains_key("MyLevel"));
+    }
+
+    #[tokio::test]
+    async fn should_restore_subscription_without_readable_value() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK\n".as_bytes());
+
+        let mut session = AsyncTesiraSession::new_from_stream(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+        session
+            .subscribe(Command::new_subscribe("Meter1", "level", [1], "MyMeter"))
+            .await
+            .unwrap();
+
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK\n".as_bytes());
+        input.extend_from_slice(
+            "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}\n"
+                .as_bytes(),
+        );
+        input.extend_from_slice("! \"publishToken\":\"MyMeter\" \"value\":-40.000000\n".as_bytes());
+        session
+            .reconnect(Cursor::new(input), Vec::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            session.recv_token().await.unwrap().value,
+            Value::Number(-40.0)
+        );
+    }
+}
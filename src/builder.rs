@@ -1,15 +1,22 @@
 //! Command builder helper
 
-use std::{error::Error, fmt::Display, ops::Deref, time::Duration};
+pub mod blocks;
+pub mod values;
 
-use crate::proto::{Command, IndexValue, InstanceTag, IntoTTP, commands::*};
-use chrono::naive::NaiveDateTime;
+use std::{error::Error, fmt::Display, ops::Deref, str::FromStr, time::Duration};
+
+use crate::proto::{
+    self, Command, FormattedDate, FromTTP, IndexValue, InstanceTag, IntoTTP, Value, ValueMap,
+    commands::*, take_key,
+};
+use values::*;
 
 #[derive(Default)]
 /// Helper to construct valid Tesira Commands
 pub struct CommandBuilder;
 
 /// Value of a delay in Tesira system
+#[derive(Debug, Clone, PartialEq)]
 pub enum DelayValue {
     /// A delay in miliseconds
     Milliseconds(Duration),
@@ -26,10 +33,9 @@ pub enum DelayValue {
 impl IntoTTP for DelayValue {
     fn into_ttp(self) -> String {
         match self {
-            DelayValue::Milliseconds(v) => format!(
-                "{{\"units\":Milliseconds \"delay\":{}}}",
-                v.as_millis().into_ttp()
-            ),
+            DelayValue::Milliseconds(v) => {
+                format!("{{\"units\":Milliseconds \"delay\":{}}}", v.into_ttp())
+            }
             DelayValue::Centimeters(v) => {
                 format!("{{\"units\":Centimeters \"delay\":{}}}", v.into_ttp())
             }
@@ -40,7 +46,121 @@ impl IntoTTP for DelayValue {
     }
 }
 
+impl FromTTP for DelayValue {
+    fn from_ttp_value(value: Value) -> Result<Self, proto::Error> {
+        let mut map = ValueMap::from_ttp_value(value)?;
+        let units: String = take_key(&mut map, "units")?;
+        let delay: Value = take_key(&mut map, "delay")?;
+        match units.as_str() {
+            "Milliseconds" => {
+                let duration = match delay {
+                    Value::Integer(v) => u64::try_from(v).ok().map(Duration::from_millis),
+                    v => Duration::try_from_secs_f64(f64::from_ttp_value(v)? / 1000.0).ok(),
+                };
+                duration
+                    .map(DelayValue::Milliseconds)
+                    .ok_or_else(|| proto::Error::InvalidValue("negative delay".to_owned()))
+            }
+            "Centimeters" => Ok(DelayValue::Centimeters(f64::from_ttp_value(delay)?)),
+            "Meters" => Ok(DelayValue::Meters(f64::from_ttp_value(delay)?)),
+            "Inches" => Ok(DelayValue::Inches(f64::from_ttp_value(delay)?)),
+            "Feet" => Ok(DelayValue::Feet(f64::from_ttp_value(delay)?)),
+            _ => Err(proto::Error::InvalidValue(format!(
+                "unknown delay units {units}"
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Value> for DelayValue {
+    type Error = proto::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::from_ttp_value(value)
+    }
+}
+
+/// A band of a parametric or graphic equalizer
+#[derive(Debug, Clone, PartialEq)]
+pub struct EqBand {
+    /// Center frequency in Hz and gain in dB
+    pub freq_gain: FreqGain,
+    /// Bandwidth in octaves, `None` for graphic equalizer bands
+    pub bandwidth: Option<f64>,
+    /// Whether the band is bypassed
+    pub bypass: bool,
+}
+
+/// A level in decibels, as opposed to a linear gain
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Db(pub f64);
+
+impl Db {
+    /// Convert a linear gain, where 1.0 is unity gain, to decibels
+    pub fn from_linear(gain: f64) -> Self {
+        Self(20.0 * gain.log10())
+    }
+
+    /// Convert to a linear gain, where 1.0 is unity gain
+    pub fn to_linear(self) -> f64 {
+        10f64.powf(self.0 / 20.0)
+    }
+}
+
+impl IntoTTP for Db {
+    fn into_ttp(self) -> String {
+        self.0.into_ttp()
+    }
+}
+
+/// Frequency and gain of a filter, as read from or written to a `freqgain` attribute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FreqGain {
+    /// Frequency in Hz
+    pub frequency: f64,
+    /// Gain in dB
+    pub gain: f64,
+}
+
+impl IntoTTP for FreqGain {
+    fn into_ttp(self) -> String {
+        format!(
+            "{{\"frequency\":{} \"gain\":{}}}",
+            self.frequency.into_ttp(),
+            self.gain.into_ttp()
+        )
+    }
+}
+
+impl From<FreqGain> for Value {
+    fn from(value: FreqGain) -> Self {
+        Value::Map(ValueMap::from([
+            ("frequency".to_owned(), Value::Number(value.frequency)),
+            ("gain".to_owned(), Value::Number(value.gain)),
+        ]))
+    }
+}
+
+impl FromTTP for FreqGain {
+    fn from_ttp_value(value: Value) -> Result<Self, proto::Error> {
+        let mut map = ValueMap::from_ttp_value(value)?;
+        Ok(FreqGain {
+            frequency: take_key(&mut map, "frequency")?,
+            gain: take_key(&mut map, "gain")?,
+        })
+    }
+}
+
+impl TryFrom<Value> for FreqGain {
+    type Error = proto::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::from_ttp_value(value)
+    }
+}
+
 /// A Tesira type of filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FilterType {
     /// Butterworth filter
     Butterworth,
@@ -60,8 +180,29 @@ impl IntoTTP for FilterType {
     }
 }
 
+impl FromTTP for FilterType {
+    fn from_ttp_value(value: Value) -> Result<Self, proto::Error> {
+        let name = String::from_ttp_value(value)?;
+        // Accept both display names and constant names such as LINKWITZ_RILEY
+        match name
+            .chars()
+            .filter(|it| it.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "butterworth" => Ok(FilterType::Butterworth),
+            "linkwitzriley" => Ok(FilterType::LinkwitzRiley),
+            "bessel" => Ok(FilterType::Bessel),
+            _ => Err(proto::Error::InvalidValue(format!(
+                "unknown filter type {name}"
+            ))),
+        }
+    }
+}
+
 /// Slope of filter
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FilterSlope(u64);
 
 /// Supported filter slopes
@@ -101,12 +242,59 @@ impl Deref for FilterSlope {
     }
 }
 
+/// Parses a slope from its integer value, checking if this slope is supported
+impl FromStr for FilterSlope {
+    type Err = InvalidSlopeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s.trim().parse().map_err(|_| InvalidSlopeError)?)
+    }
+}
+
 impl IntoTTP for FilterSlope {
     fn into_ttp(self) -> String {
         self.0.into_ttp()
     }
 }
 
+impl FromTTP for FilterSlope {
+    fn from_ttp_value(value: Value) -> Result<Self, proto::Error> {
+        let slope = f64::from_ttp_value(value)?;
+        if slope.fract() != 0.0 || slope < 0.0 {
+            return Err(proto::Error::InvalidValue(format!("invalid slope {slope}")));
+        }
+        Self::new(slope as u64)
+            .map_err(|_| proto::Error::InvalidValue(format!("invalid slope {slope}")))
+    }
+}
+
+/// Type and slope of a filter, as read from a `typeslope` attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeSlope {
+    /// Type of filter
+    pub filter_type: FilterType,
+    /// Slope of filter
+    pub filter_slope: FilterSlope,
+}
+
+impl FromTTP for TypeSlope {
+    fn from_ttp_value(value: Value) -> Result<Self, proto::Error> {
+        let mut map = ValueMap::from_ttp_value(value)?;
+        Ok(TypeSlope {
+            filter_type: take_key(&mut map, "type")?,
+            filter_slope: take_key(&mut map, "slope")?,
+        })
+    }
+}
+
+impl TryFrom<Value> for TypeSlope {
+    type Error = proto::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::from_ttp_value(value)
+    }
+}
+
 /// Provided slope value is invalid
 #[derive(Debug)]
 pub struct InvalidSlopeError;
@@ -123,4 +311,740 @@ impl Display for InvalidSlopeError {
     }
 }
 
+/// Provided value is out of the range allowed by an attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutOfRangeError {
+    /// Value that was attempted
+    pub value: f64,
+    /// Minimum allowed value
+    pub min: f64,
+    /// Maximum allowed value
+    pub max: f64,
+}
+
+impl OutOfRangeError {
+    /// Check a value is within bounds, returning it unchanged if so
+    pub fn check(value: f64, min: f64, max: f64) -> Result<f64, OutOfRangeError> {
+        if !(min..=max).contains(&value) {
+            return Err(OutOfRangeError { value, min, max });
+        }
+        Ok(value)
+    }
+}
+
+impl Error for OutOfRangeError {}
+
+impl Display for OutOfRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Value {} is out of range, allowed values are {} to {}",
+            self.value, self.min, self.max
+        )
+    }
+}
+
+impl CommandBuilder {
+    /// Set any attribute of a block with an arbitrary value
+    ///
+    /// This is an escape hatch for attributes without a typed setter,
+    /// generated [values] can be used to provide a typed value.
+    pub fn raw_set<'a>(
+        self,
+        instance_tag: impl Into<InstanceTag>,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        value: impl IntoTTP,
+    ) -> Command<'a> {
+        Command::new_set(instance_tag, attribute, indexes, value)
+    }
+
+    /// Build any command on any attribute, pushing indexes and values manually
+    ///
+    /// This is an escape hatch for attributes missing from the block catalog.
+    ///
+    /// ```
+    /// use tesira_text_protocol::{Command, proto::{IntoTTP, commands::COMMAND_GET}};
+    ///
+    /// let command = Command::builder()
+    ///     .raw("Level3", COMMAND_GET, "newAttribute")
+    ///     .index(2)
+    ///     .build();
+    /// assert_eq!(command.into_ttp(), "Level3 get newAttribute 2");
+    /// ```
+    pub fn raw(
+        self,
+        instance_tag: impl Into<InstanceTag>,
+        command: &'static str,
+        attribute: &'static str,
+    ) -> RawCommandBuilder {
+        RawCommandBuilder(Command {
+            instance_tag: instance_tag.into(),
+            command,
+            attribute,
+            indexes: Vec::new(),
+            values: Vec::new(),
+        })
+    }
+
+    /// Operate on a Matrix Mixer block, the mixer type with crosspoints
+    pub fn mixer(self, instance_tag: impl Into<InstanceTag>) -> MatrixMixerCommandBuilder {
+        self.matrix_mixer(instance_tag)
+    }
+}
+
+/// Builder of a command on an arbitrary attribute, see [CommandBuilder::raw]
+#[derive(Debug, Clone)]
+pub struct RawCommandBuilder(Command<'static>);
+
+impl RawCommandBuilder {
+    /// Append an index, such as a channel number
+    pub fn index(mut self, index: IndexValue) -> Self {
+        self.0.indexes.push(index);
+        self
+    }
+
+    /// Append a value
+    pub fn value(mut self, value: impl IntoTTP) -> Self {
+        self.0.values.push(value.into_ttp());
+        self
+    }
+
+    /// Append a value if it is `Some`, `None` adds no value
+    pub fn optional_value(mut self, value: Option<impl IntoTTP>) -> Self {
+        self.0.append_optional_value(value);
+        self
+    }
+
+    /// Get the built command
+    pub fn build(self) -> Command<'static> {
+        self.0
+    }
+}
+
+impl From<RawCommandBuilder> for Command<'static> {
+    fn from(value: RawCommandBuilder) -> Self {
+        value.build()
+    }
+}
+
+/// Commands common to every block, used to write typed builders of custom blocks
+///
+/// Blocks missing from the catalog (custom or third-party blocks) can get a typed builder
+/// by implementing this trait and exposing it on [CommandBuilder] with an extension trait.
+///
+/// ```
+/// use tesira_text_protocol::{
+///     Command,
+///     builder::{BlockCommandBuilder, CommandBuilder},
+///     proto::{IndexValue, InstanceTag, IntoTTP},
+/// };
+///
+/// /// Operate on my custom gain block
+/// pub struct MyGainCommandBuilder(InstanceTag);
+///
+/// impl BlockCommandBuilder for MyGainCommandBuilder {
+///     fn instance_tag(&self) -> &InstanceTag {
+///         &self.0
+///     }
+/// }
+///
+/// impl MyGainCommandBuilder {
+///     /// Get gain of a channel
+///     pub fn gain(&self, channel_index: IndexValue) -> Command<'static> {
+///         self.get("gain", [channel_index])
+///     }
+///
+///     /// Set gain of a channel
+///     pub fn set_gain(&self, channel_index: IndexValue, value: f64) -> Command<'static> {
+///         self.set("gain", [channel_index], value)
+///     }
+/// }
+///
+/// /// Custom blocks of my installation
+/// pub trait MyBlocks {
+///     /// Operate on my custom gain block
+///     fn my_gain(self, instance_tag: impl Into<InstanceTag>) -> MyGainCommandBuilder;
+/// }
+///
+/// impl MyBlocks for CommandBuilder {
+///     fn my_gain(self, instance_tag: impl Into<InstanceTag>) -> MyGainCommandBuilder {
+///         MyGainCommandBuilder(instance_tag.into())
+///     }
+/// }
+///
+/// assert_eq!(
+///     Command::builder().my_gain("Gain1").set_gain(1, -3.5).into_ttp(),
+///     "Gain1 set gain 1 -3.5"
+/// );
+/// ```
+pub trait BlockCommandBuilder {
+    /// Instance tag of the block to operate on
+    fn instance_tag(&self) -> &InstanceTag;
+
+    /// Create a "get" command on an attribute of this block
+    fn get<'a>(&self, attribute: &'a str, indexes: impl Into<Vec<IndexValue>>) -> Command<'a> {
+        Command::new_get(self.instance_tag().clone(), attribute, indexes)
+    }
+
+    /// Create a "set" command on an attribute of this block
+    fn set<'a>(
+        &self,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        value: impl IntoTTP,
+    ) -> Command<'a> {
+        Command::new_set(self.instance_tag().clone(), attribute, indexes, value)
+    }
+
+    /// Create an "increment" command on an attribute of this block
+    fn increment<'a>(
+        &self,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        amount: impl IntoTTP,
+    ) -> Command<'a> {
+        Command::new_increment(self.instance_tag().clone(), attribute, indexes, amount)
+    }
+
+    /// Create a "decrement" command on an attribute of this block
+    fn decrement<'a>(
+        &self,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        amount: impl IntoTTP,
+    ) -> Command<'a> {
+        Command::new_decrement(self.instance_tag().clone(), attribute, indexes, amount)
+    }
+
+    /// Create a "subscribe" command on an attribute of this block
+    fn subscribe<'a>(
+        &self,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        subscription_label: impl Into<String>,
+    ) -> Command<'a> {
+        Command::new_subscribe(
+            self.instance_tag().clone(),
+            attribute,
+            indexes,
+            subscription_label,
+        )
+    }
+
+    /// Create an "unsubscribe" command on an attribute of this block
+    fn unsubscribe<'a>(
+        &self,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        subscription_label: impl Into<String>,
+    ) -> Command<'a> {
+        Command::new_unsubscribe(
+            self.instance_tag().clone(),
+            attribute,
+            indexes,
+            subscription_label,
+        )
+    }
+}
+
 include!("../generated/tesira-blocks.rs");
+
+impl StandardMixerCommandBuilder {
+    /// Get mute state of a Standard Mixer input
+    pub fn input_mute(&self, input_index: IndexValue) -> Command<'static> {
+        self.inputmute(input_index)
+    }
+
+    /// Mute or unmute a Standard Mixer input
+    pub fn set_input_mute(&self, input_index: IndexValue, value: bool) -> Command<'static> {
+        self.set_inputmute(input_index, value)
+    }
+
+    /// Get mute state of a Standard Mixer output
+    pub fn output_mute(&self, output_index: IndexValue) -> Command<'static> {
+        self.outputmute(output_index)
+    }
+
+    /// Mute or unmute a Standard Mixer output
+    pub fn set_output_mute(&self, output_index: IndexValue, value: bool) -> Command<'static> {
+        self.set_outputmute(output_index, value)
+    }
+}
+
+impl MatrixMixerCommandBuilder {
+    /// Get mute state of a Matrix Mixer input
+    pub fn input_mute(&self, input_index: IndexValue) -> Command<'static> {
+        self.inputmute(input_index)
+    }
+
+    /// Mute or unmute a Matrix Mixer input
+    pub fn set_input_mute(&self, input_index: IndexValue, value: bool) -> Command<'static> {
+        self.set_inputmute(input_index, value)
+    }
+
+    /// Get mute state of a Matrix Mixer output
+    pub fn output_mute(&self, output_index: IndexValue) -> Command<'static> {
+        self.outputmute(output_index)
+    }
+
+    /// Mute or unmute a Matrix Mixer output
+    pub fn set_output_mute(&self, output_index: IndexValue, value: bool) -> Command<'static> {
+        self.set_outputmute(output_index, value)
+    }
+
+    /// Get whether an input is routed to an output
+    pub fn get_crosspoint(
+        &self,
+        input_index: IndexValue,
+        output_index: IndexValue,
+    ) -> Command<'static> {
+        self.crosspointlevelstate(input_index, output_index)
+    }
+
+    /// Route an input to an output or remove the route
+    pub fn set_crosspoint(
+        &self,
+        input_index: IndexValue,
+        output_index: IndexValue,
+        on: bool,
+    ) -> Command<'static> {
+        self.set_crosspointlevelstate(input_index, output_index, on)
+    }
+}
+
+impl SourceSelectorCommandBuilder {
+    /// Get index of the selected source, 0 when no source is selected
+    pub fn selected(&self) -> Command<'static> {
+        self.sourceselection()
+    }
+
+    /// Select a source, sources are numbered from 1
+    ///
+    /// Use [Self::set_sourceselection] with 0 to deselect every source.
+    pub fn select(&self, source_index: IndexValue) -> Result<Command<'static>, OutOfRangeError> {
+        if source_index == 0 {
+            return Err(OutOfRangeError {
+                value: 0.0,
+                min: 1.0,
+                max: Self::SOURCESELECTION_MAX,
+            });
+        }
+        self.set_sourceselection(source_index as f64)
+    }
+}
+
+impl DeviceServicesCommandBuilder {
+    /// Recall a preset by its identifier, presets are numbered from 1001
+    pub fn recall_preset(&self, preset_id: IndexValue) -> Command<'static> {
+        Command::new_recall_preset(preset_id)
+    }
+
+    /// Recall a preset by its name
+    pub fn recall_preset_by_name(&self, name: &str) -> Command<'static> {
+        Command::new_recall_preset_by_name(name)
+    }
+
+    /// Reboot device
+    pub fn reboot(&self) -> Command<'static> {
+        Command::new_reboot()
+    }
+}
+
+impl GraphicEqualizerCommandBuilder {
+    /// Center frequency in Hz of a band, given the band count of the block
+    ///
+    /// Bands of 10, 15 and 31 band blocks are spaced by 1, 2/3 and 1/3 octave on base 10
+    /// ISO centers, such as 31.6 Hz for nominal 31.5 Hz. Other counts return `None`.
+    pub fn center_frequency(band_count: u64, band: u64) -> Option<f64> {
+        // Tenths of decade from 1 kHz of first band, and between bands
+        let (first, step) = match band_count {
+            10 => (-15, 3),
+            15 => (-16, 2),
+            31 => (-17, 1),
+            _ => return None,
+        };
+        if band == 0 || band > band_count {
+            return None;
+        }
+        let tenths = first + step * (band as i32 - 1);
+        Some(1000.0 * 10f64.powf(tenths as f64 / 10.0))
+    }
+}
+
+impl LevelCommandBuilder {
+    /// Set level of a Level block channel in dB
+    ///
+    /// Level is clamped to the block range, from [Self::LEVEL_MIN] to [Self::LEVEL_MAX].
+    /// A NaN level is sent as [Self::LEVEL_MIN], the quietest level.
+    pub fn set_level_db(&self, channel_index: IndexValue, level: Db) -> Command<'static> {
+        let level = if level.0.is_nan() {
+            Self::LEVEL_MIN
+        } else {
+            level.0.clamp(Self::LEVEL_MIN, Self::LEVEL_MAX)
+        };
+        self.set_level(channel_index, level)
+            .expect("level is clamped to block range")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        BlockCommandBuilder, CommandBuilder, Db, DelayValue, FilterSlope, FilterType, FreqGain,
+        GraphicEqualizerCommandBuilder, InvalidSlopeError, LevelCommandBuilder, OutOfRangeError,
+        TypeSlope, values::AudioMeterType,
+    };
+    use crate::proto::{
+        Command, IndexValue, InstanceTag, IntoTTP, OkResponse, Response, Value,
+        commands::{COMMAND_GET, COMMAND_SET},
+    };
+
+    struct CustomMeterCommandBuilder(InstanceTag);
+
+    impl BlockCommandBuilder for CustomMeterCommandBuilder {
+        fn instance_tag(&self) -> &InstanceTag {
+            &self.0
+        }
+    }
+
+    impl CustomMeterCommandBuilder {
+        fn peak(&self, channel_index: IndexValue) -> Command<'static> {
+            self.get("peak", [channel_index])
+        }
+
+        fn subscribe_peak(
+            &self,
+            channel_index: IndexValue,
+            subscription_label: impl Into<String>,
+        ) -> Command<'static> {
+            self.subscribe("peak", [channel_index], subscription_label)
+        }
+    }
+
+    trait CustomBlocks {
+        fn custom_meter(self, instance_tag: impl Into<InstanceTag>) -> CustomMeterCommandBuilder;
+    }
+
+    impl CustomBlocks for CommandBuilder {
+        fn custom_meter(self, instance_tag: impl Into<InstanceTag>) -> CustomMeterCommandBuilder {
+            CustomMeterCommandBuilder(instance_tag.into())
+        }
+    }
+
+    #[test]
+    fn should_build_custom_block_commands() {
+        let meter = Command::builder().custom_meter("Meter1");
+        assert_eq!(meter.peak(2).into_ttp(), "Meter1 get peak 2");
+        assert_eq!(
+            meter.subscribe_peak(2, "MyPeak").into_ttp(),
+            "Meter1 subscribe peak 2 MyPeak"
+        );
+    }
+
+    #[test]
+    fn should_build_raw_commands() {
+        assert_eq!(
+            Command::builder()
+                .raw("Mixer1", COMMAND_SET, "crosspointLevel")
+                .index(1)
+                .index(2)
+                .value(-10.0)
+                .optional_value(None::<f64>)
+                .build()
+                .into_ttp(),
+            "Mixer1 set crosspointLevel 1 2 -10"
+        );
+        assert_eq!(
+            Command::from(Command::builder().raw("DEVICE", COMMAND_GET, "version")).into_ttp(),
+            "DEVICE get version"
+        );
+    }
+
+    #[test]
+    fn should_raw_set_generated_value() {
+        assert_eq!(
+            Command::builder()
+                .raw_set("AudioMeter1", "type", [1], AudioMeterType::Rms)
+                .into_ttp(),
+            "AudioMeter1 set type 1 RMS"
+        );
+    }
+
+    #[test]
+    fn should_build_generated_toggle_commands() {
+        assert_eq!(
+            Command::builder().level("Level3").toggle_mute(1).into_ttp(),
+            "Level3 toggle mute 1"
+        );
+    }
+
+    #[test]
+    fn should_build_generated_increment_commands() {
+        let level = Command::builder().level("Level3");
+        assert_eq!(
+            level.increment_level(1, 3.0).into_ttp(),
+            "Level3 increment level 1 3"
+        );
+        assert_eq!(
+            level.decrement_level(2, 1.5).into_ttp(),
+            "Level3 decrement level 2 1.5"
+        );
+    }
+
+    #[test]
+    fn should_check_range_of_generated_setters() {
+        let level = Command::builder().level("Level3");
+        assert_eq!(
+            level.set_level(1, -10.0).unwrap().into_ttp(),
+            "Level3 set level 1 -10"
+        );
+        assert_eq!(
+            level.set_level(1, 12.0).unwrap().into_ttp(),
+            "Level3 set level 1 12"
+        );
+        assert_eq!(
+            level.set_level(1, 200.0).unwrap_err(),
+            OutOfRangeError {
+                value: 200.0,
+                min: -100.0,
+                max: 12.0
+            }
+        );
+        assert!(level.set_level(1, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn should_build_generated_telephony_commands() {
+        let voip = Command::builder().voip_controlstatus("VoIP1");
+        assert_eq!(
+            voip.dial(1, 1, "5551212").into_ttp(),
+            "VoIP1 dial 1 1 \"5551212\""
+        );
+        assert_eq!(
+            voip.dial(1, 1, "555\"1").into_ttp(),
+            "VoIP1 dial 1 1 \"555\\\"1\""
+        );
+        assert_eq!(voip.dtmf(1, "5#").into_ttp(), "VoIP1 dtmf 1 \"5#\"");
+        assert_eq!(
+            voip.dtmf(1, "5\nDEVICE reboot").into_ttp(),
+            "VoIP1 dtmf 1 \"5\\nDEVICE reboot\""
+        );
+        assert_eq!(voip.off_hook(1, 2).into_ttp(), "VoIP1 offHook 1 2");
+        assert_eq!(
+            Command::builder()
+                .ti_controlstatus("TI1")
+                .answer()
+                .into_ttp(),
+            "TI1 answer"
+        );
+    }
+
+    #[test]
+    fn should_build_generated_command_and_string_setter() {
+        assert_eq!(
+            Command::builder()
+                .command_string("CmdStr1")
+                .set_labelcommand(2, "Power On", "PWR \"1\"")
+                .into_ttp(),
+            "CmdStr1 set labelCommand 2 {\"label\":\"Power On\" \"command\":\"PWR \\\"1\\\"\"}"
+        );
+    }
+
+    #[test]
+    fn should_build_mixer_mute_commands() {
+        let mixer = Command::builder().standard_mixer("Mixer1");
+        assert_eq!(mixer.input_mute(2).into_ttp(), "Mixer1 get inputMute 2");
+        assert_eq!(
+            mixer.set_input_mute(2, true).into_ttp(),
+            "Mixer1 set inputMute 2 true"
+        );
+        assert_eq!(mixer.output_mute(1).into_ttp(), "Mixer1 get outputMute 1");
+        assert_eq!(
+            mixer.set_output_mute(1, false).into_ttp(),
+            "Mixer1 set outputMute 1 false"
+        );
+
+        let mixer = Command::builder().matrix_mixer("Matrix1");
+        assert_eq!(
+            mixer.set_input_mute(4, false).into_ttp(),
+            "Matrix1 set inputMute 4 false"
+        );
+        assert_eq!(
+            mixer.set_output_mute(3, true).into_ttp(),
+            "Matrix1 set outputMute 3 true"
+        );
+    }
+
+    #[test]
+    fn should_build_crosspoint_commands() {
+        let mixer = CommandBuilder.mixer("Mixer1");
+        assert_eq!(
+            mixer.get_crosspoint(1, 3).into_ttp(),
+            "Mixer1 get crosspointLevelState 1 3"
+        );
+        assert_eq!(
+            mixer.set_crosspoint(2, 4, true).into_ttp(),
+            "Mixer1 set crosspointLevelState 2 4 true"
+        );
+        assert_eq!(
+            mixer.set_crosspoint(4, 2, false).into_ttp(),
+            "Mixer1 set crosspointLevelState 4 2 false"
+        );
+    }
+
+    #[test]
+    fn should_build_source_selector_commands() {
+        let selector = CommandBuilder.source_selector("SS1");
+        assert_eq!(selector.selected().into_ttp(), "SS1 get sourceSelection");
+        assert_eq!(
+            selector.select(3).unwrap().into_ttp(),
+            "SS1 set sourceSelection 3"
+        );
+        assert_eq!(
+            selector.select(0).unwrap_err(),
+            OutOfRangeError {
+                value: 0.0,
+                min: 1.0,
+                max: 32.0
+            }
+        );
+        assert!(selector.select(33).is_err());
+    }
+
+    #[test]
+    fn should_build_recall_preset_commands() {
+        let device = CommandBuilder.device();
+        assert_eq!(
+            device.recall_preset(1001).into_ttp(),
+            "DEVICE recallPreset 1001"
+        );
+        assert_eq!(
+            device.recall_preset_by_name("Night Mode").into_ttp(),
+            "DEVICE recallPresetByName \"Night Mode\""
+        );
+        assert_eq!(device.reboot().into_ttp(), "DEVICE reboot");
+    }
+
+    #[test]
+    fn should_parse_filter_slope() {
+        assert_eq!(*"24".parse::<FilterSlope>().unwrap(), 24);
+        assert!(matches!(
+            "25".parse::<FilterSlope>(),
+            Err(InvalidSlopeError)
+        ));
+        assert!(matches!(
+            "steep".parse::<FilterSlope>(),
+            Err(InvalidSlopeError)
+        ));
+    }
+
+    #[test]
+    fn should_read_type_slope() {
+        let Response::Ok(OkResponse::WithValue(value)) =
+            Response::parse_ttp("+OK \"value\":{\"type\":LINKWITZ_RILEY \"slope\":24}").unwrap()
+        else {
+            panic!("Expected a value")
+        };
+        assert_eq!(
+            TypeSlope::try_from(value).unwrap(),
+            TypeSlope {
+                filter_type: FilterType::LinkwitzRiley,
+                filter_slope: FilterSlope::TWENTYFOUR,
+            }
+        );
+
+        let Response::Ok(OkResponse::WithValue(value)) =
+            Response::parse_ttp("+OK \"value\":{\"type\":BUTTERWORTH \"slope\":25}").unwrap()
+        else {
+            panic!("Expected a value")
+        };
+        assert!(TypeSlope::try_from(value).is_err());
+    }
+
+    #[test]
+    fn should_round_trip_freq_gain() {
+        let freq_gain = FreqGain {
+            frequency: 1000.0,
+            gain: -3.5,
+        };
+        assert_eq!(freq_gain.into_ttp(), "{\"frequency\":1000 \"gain\":-3.5}");
+        assert_eq!(
+            FreqGain::try_from(Value::from(freq_gain)).unwrap(),
+            freq_gain
+        );
+
+        let Response::Ok(OkResponse::WithValue(value)) =
+            Response::parse_ttp(&format!("+OK \"value\":{}", freq_gain.into_ttp())).unwrap()
+        else {
+            panic!("Expected a value")
+        };
+        assert_eq!(FreqGain::try_from(value).unwrap(), freq_gain);
+        assert!(FreqGain::try_from(Value::Number(1000.0)).is_err());
+    }
+
+    #[test]
+    fn should_round_trip_delay_value() {
+        for delay in [
+            DelayValue::Milliseconds(Duration::from_millis(20)),
+            DelayValue::Centimeters(12.5),
+            DelayValue::Meters(3.0),
+            DelayValue::Inches(-1.5),
+            DelayValue::Feet(10.25),
+        ] {
+            let Response::Ok(OkResponse::WithValue(value)) =
+                Response::parse_ttp(&format!("+OK \"value\":{}", delay.clone().into_ttp()))
+                    .unwrap()
+            else {
+                panic!("Expected a value")
+            };
+            assert_eq!(DelayValue::try_from(value).unwrap(), delay);
+        }
+    }
+
+    #[test]
+    fn should_give_graphic_eq_center_frequencies() {
+        let center = |count, band| {
+            GraphicEqualizerCommandBuilder::center_frequency(count, band)
+                .map(|it| (it * 10.0).round() / 10.0)
+        };
+        assert_eq!(center(31, 1), Some(20.0));
+        assert_eq!(center(31, 18), Some(1000.0));
+        assert_eq!(center(31, 31), Some(19952.6));
+        assert_eq!(center(15, 1), Some(25.1));
+        assert_eq!(center(15, 9), Some(1000.0));
+        assert_eq!(center(10, 6), Some(1000.0));
+        assert_eq!(center(10, 11), None);
+        assert_eq!(center(12, 1), None);
+    }
+
+    #[test]
+    fn should_set_level_in_db() {
+        assert_eq!(Db(-10.0).into_ttp(), "-10");
+        assert!((Db::from_linear(0.5).0 - -6.0206).abs() < 1e-4);
+        assert!((Db(-6.0206).to_linear() - 0.5).abs() < 1e-4);
+
+        let level = CommandBuilder.level("Level1");
+        assert_eq!(
+            level.set_level_db(1, Db(-10.0)).into_ttp(),
+            "Level1 set level 1 -10"
+        );
+        assert_eq!(
+            level.set_level_db(1, Db(20.0)).into_ttp(),
+            "Level1 set level 1 12"
+        );
+        assert_eq!(
+            level.set_level_db(1, Db(-200.0)).into_ttp(),
+            "Level1 set level 1 -100"
+        );
+        assert_eq!(
+            level.set_level_db(1, Db(f64::NAN)).into_ttp(),
+            "Level1 set level 1 -100"
+        );
+        assert_eq!(LevelCommandBuilder::LEVEL_MAX, 12.0);
+    }
+}
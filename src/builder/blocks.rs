@@ -0,0 +1,67 @@
+//! Runtime description of blocks supported by [CommandBuilder](super::CommandBuilder)
+//!
+//! Generated from the same block catalog as builders, so a tool can list supported blocks
+//! and attributes without consulting the catalog.
+
+/// A block type and its attributes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockMeta {
+    /// Block type name, such as `Level`
+    pub name: &'static str,
+    /// Group of block, such as `Mixer Blocks`
+    pub group: &'static str,
+    /// Attributes of block
+    pub attributes: &'static [AttributeMeta],
+}
+
+/// An attribute of a block type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttributeMeta {
+    /// Attribute string sent to device, such as `level`
+    pub name: &'static str,
+    /// Human readable description
+    pub description: &'static str,
+    /// Command strings supported by attribute, such as `get` or `set`
+    pub commands: &'static [&'static str],
+    /// Names of indexes expected by attribute, such as `channel`
+    pub indexes: &'static [&'static str],
+    /// Type of value as named in catalog, such as `range` or `discrete`
+    pub value_type: &'static str,
+}
+
+include!("../../generated/tesira-blocks-meta.rs");
+
+/// Names of every block type, sorted
+pub fn blocks() -> impl Iterator<Item = &'static str> {
+    BLOCKS.iter().map(|it| it.name)
+}
+
+/// Find a block type by name
+pub fn block(name: &str) -> Option<&'static BlockMeta> {
+    BLOCKS
+        .binary_search_by(|it| it.name.cmp(name))
+        .ok()
+        .map(|index| &BLOCKS[index])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{block, blocks};
+
+    #[test]
+    fn should_describe_level_block() {
+        assert!(blocks().any(|it| it == "Level"));
+
+        let level = block("Level").unwrap();
+        let attribute = level
+            .attributes
+            .iter()
+            .find(|it| it.name == "level")
+            .unwrap();
+        assert!(attribute.commands.contains(&"set"));
+        assert_eq!(attribute.indexes, ["channel"]);
+        assert_eq!(attribute.value_type, "range");
+
+        assert!(block("Unknown Block").is_none());
+    }
+}
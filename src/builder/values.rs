@@ -0,0 +1,44 @@
+//! Allowed values of discrete block attributes
+//!
+//! Those types are generated from the block catalog and are used by typed setters of
+//! [CommandBuilder](super::CommandBuilder). They can also be passed to
+//! [CommandBuilder::raw_set](super::CommandBuilder::raw_set) to target any attribute.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proto::IntoTTP;
+
+/// Provided string is not an allowed value of the attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownValueError(pub String);
+
+impl Error for UnknownValueError {}
+
+impl Display for UnknownValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown value '{}'", self.0)
+    }
+}
+
+include!("../../generated/tesira-values.rs");
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{AudioMeterType, UnknownValueError};
+
+    #[test]
+    fn should_round_trip_generated_values_through_text() {
+        assert_eq!("RMS".parse::<AudioMeterType>(), Ok(AudioMeterType::Rms));
+        assert_eq!(AudioMeterType::Rms.to_string(), "RMS");
+        assert_eq!(
+            "rms".parse::<AudioMeterType>(),
+            Err(UnknownValueError("rms".to_owned()))
+        );
+    }
+}
@@ -1,28 +1,213 @@
 #![warn(missing_docs)]
-#![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "ssh", doc = include_str!("../README.md"))]
+#![cfg_attr(
+    not(feature = "ssh"),
+    doc = "Implementation of Tesira Text Protocol to communicate with biamp Tesira devices"
+)]
 
+#[cfg(feature = "tokio")]
+pub mod async_session;
 pub mod builder;
+mod macros;
+pub mod metrics;
+#[cfg(feature = "test-util")]
+pub mod mock;
 pub mod proto;
+pub mod split;
+#[cfg(feature = "telnet")]
+pub mod telnet;
 
-pub use builder::CommandBuilder;
+#[cfg(feature = "tokio")]
+pub use async_session::AsyncTesiraSession;
+pub use builder::{CommandBuilder, EqBand};
 pub use chrono::naive::NaiveDateTime;
 pub use proto::Command;
+pub use proto::decoder::ResponseDecoder;
+pub use proto::fault::FaultStatus;
+pub use proto::network::DeviceNetworkStatus;
+pub use split::{TesiraReader, TesiraWriter};
 
 use std::{
-    collections::{HashSet, VecDeque},
-    io::{self, BufRead, BufReader, Read, Write},
-    net::ToSocketAddrs,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Display},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    iter, mem,
+    net::TcpStream,
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use thiserror::Error;
 
-use crate::proto::{ErrResponse, IntoTTP, OkResponse, PublishToken, Response, Value};
+use crate::builder::{FreqGain, GraphicEqualizerCommandBuilder};
+use crate::metrics::MetricSink;
+use crate::proto::{
+    ErrResponse, FromTTP, InstanceTag, IntoTTP, OkResponse, PublishToken, Response, Value,
+    fault::parse_fault_list,
+};
 
 /// Follows an active Tesira Text Protocol session
 pub struct TesiraSession<R: Read, W: Write> {
     read_stream: BufReader<R>,
-    write_stream: W,
+    write_stream: BufWriter<W>,
+    read_buffer: String,
+    last_echo: Option<String>,
     pending_token: VecDeque<PublishToken>,
+    current_values: HashMap<String, Value>,
+    subscriptions: HashMap<String, Sender<PublishToken>>,
+    subscribe_commands: HashMap<String, String>,
+    multiline_errors: bool,
+    options: SessionOptions,
+    command_timeout: Option<Duration>,
+    keepalive: Option<Duration>,
+    last_sent: Instant,
+    stats: SessionStats,
+    transport: Option<Box<dyn Transport>>,
+}
+
+/// Options applied when opening a session
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionOptions {
+    /// Maximum number of lines read while waiting for the welcome banner
+    pub banner_max_lines: usize,
+    /// Maximum number of bytes read while waiting for the welcome banner
+    pub banner_max_bytes: usize,
+    /// Maximum length of a response in bytes, see [Error::ResponseTooLarge]
+    pub max_response_len: usize,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            banner_max_lines: 100,
+            banner_max_bytes: 16 * 1024,
+            max_response_len: 1024 * 1024,
+        }
+    }
+}
+
+/// Counters of session activity, see [TesiraSession::stats]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SessionStats {
+    /// Command lines written to device
+    pub commands_sent: u64,
+    /// Positive responses received
+    pub ok_responses: u64,
+    /// Error responses received
+    pub err_responses: u64,
+    /// Publish tokens received
+    pub publish_tokens: u64,
+    /// Response lines that could not be parsed
+    pub parse_failures: u64,
+}
+
+/// Retry policy of [TesiraSession::send_command_retry]
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Factor applied to the delay after each retry
+    pub multiplier: f64,
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Whether an error response is transient and the command can be sent again
+    pub retriable: fn(&ErrResponse) -> bool,
+}
+
+impl RetryPolicy {
+    /// Retry errors matching `retriable` up to 3 attempts, waiting 100ms then 200ms
+    pub fn new(retriable: fn(&ErrResponse) -> bool) -> Self {
+        Self {
+            base_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_attempts: 3,
+            retriable,
+        }
+    }
+}
+
+/// Controls of the underlying transport that can't be expressed with [Read] and [Write]
+trait Transport: Send {
+    /// Set timeout of blocking reads, `None` waits forever
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Close the connection with device
+    fn close(&mut self) -> io::Result<()>;
+
+    /// SSH transport carrying the connection, if any
+    #[cfg(feature = "ssh")]
+    fn ssh(&self) -> Option<&SshTransport> {
+        None
+    }
+}
+
+impl Transport for TcpStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.shutdown(std::net::Shutdown::Both)
+    }
+}
+
+/// SSH session and the channel opened on it
+#[cfg(feature = "ssh")]
+struct SshTransport {
+    session: ssh2::Session,
+    channel: ssh2::Channel,
+    /// Set when this crate opened the session, so it can be opened again
+    credentials: Option<SshCredentials>,
+}
+
+/// Everything needed to open an SSH session again
+#[cfg(feature = "ssh")]
+#[derive(Clone)]
+struct SshCredentials {
+    addrs: Vec<std::net::SocketAddr>,
+    username: String,
+    password: String,
+}
+
+#[cfg(feature = "ssh")]
+impl SshCredentials {
+    /// Connect, handshake and authenticate a new SSH session
+    fn connect(&self) -> Result<ssh2::Session, Error> {
+        let connection = std::net::TcpStream::connect(&self.addrs[..])?;
+
+        let mut ssh = ssh2::Session::new()?;
+        ssh.set_tcp_stream(connection);
+        ssh.handshake()?;
+        ssh.userauth_keyboard_interactive(&self.username, &mut SshPassword(&self.password))?;
+        Ok(ssh)
+    }
+}
+
+#[cfg(feature = "ssh")]
+impl Transport for SshTransport {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        // A zero timeout means no timeout for libssh2
+        self.session.set_timeout(
+            timeout
+                .map(|it| it.as_millis().clamp(1, u32::MAX as u128) as u32)
+                .unwrap_or(0),
+        );
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.channel.close()?;
+        self.channel.wait_close()?;
+        Ok(())
+    }
+
+    fn ssh(&self) -> Option<&SshTransport> {
+        Some(self)
+    }
 }
 
 #[cfg(feature = "ssh")]
@@ -38,48 +223,379 @@ impl ssh2::KeyboardInteractivePrompt for SshPassword<'_> {
 #[cfg(feature = "ssh")]
 impl TesiraSession<ssh2::Channel, ssh2::Channel> {
     /// Connect to tesira device over SSH
+    ///
+    /// Address and credentials are kept so that [TesiraSession::reconnect] can open
+    /// a new SSH session once the connection is lost.
     pub fn new_from_ssh(
-        hostname: impl ToSocketAddrs,
+        hostname: impl std::net::ToSocketAddrs,
         username: &str,
         password: &str,
     ) -> Result<Self, Error> {
-        let connection = std::net::TcpStream::connect(hostname)?;
-
-        let mut ssh = ssh2::Session::new()?;
-        ssh.set_tcp_stream(connection);
-        ssh.handshake()?;
-        ssh.userauth_keyboard_interactive(username, &mut SshPassword(password))?;
+        let credentials = SshCredentials {
+            addrs: hostname.to_socket_addrs()?.collect(),
+            username: username.to_owned(),
+            password: password.to_owned(),
+        };
+        let session = credentials.connect()?;
+        Self::new_from_ssh_transport(session, Some(credentials), SessionOptions::default())
+    }
 
-        Self::new_from_ssh_session(&ssh)
+    /// Connect to tesira device over SSH given a `host[:port]` string
+    ///
+    /// Port 22 is used when `hostname` has no `:port` suffix, IPv6 addresses with a port
+    /// must be enclosed in brackets such as `[::1]:22`.
+    pub fn new_from_ssh_host(
+        hostname: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<Self, Error> {
+        Self::new_from_ssh(split_host_port(hostname, 22)?, username, password)
     }
 
     /// Connect to tesira from an **established** and **authenticated** ssh session
     /// It will create a new channel to communicate with device
     pub fn new_from_ssh_session(session: &ssh2::Session) -> Result<Self, Error> {
-        let mut channel = session.channel_session()?;
-        channel.request_pty("ansi", None, None)?;
-        channel.shell()?;
-        Self::new_from_stream(channel.clone(), channel)
+        Self::new_from_ssh_session_with_options(session, SessionOptions::default())
+    }
+
+    /// Connect to tesira from an established ssh session with custom options
+    ///
+    /// Options are kept and applied again by [TesiraSession::reconnect].
+    pub fn new_from_ssh_session_with_options(
+        session: &ssh2::Session,
+        options: SessionOptions,
+    ) -> Result<Self, Error> {
+        Self::new_from_ssh_transport(session.clone(), None, options)
+    }
+
+    fn new_from_ssh_transport(
+        session: ssh2::Session,
+        credentials: Option<SshCredentials>,
+        options: SessionOptions,
+    ) -> Result<Self, Error> {
+        let channel = open_ssh_shell(&session)?;
+        let mut new_self =
+            Self::new_from_stream_with_options(channel.clone(), channel.clone(), options)?;
+        new_self.transport = Some(Box::new(SshTransport {
+            session,
+            channel,
+            credentials,
+        }));
+        Ok(new_self)
+    }
+
+    /// Connect again to device and restore subscriptions
+    ///
+    /// Sessions opened with [TesiraSession::new_from_ssh] connect, handshake and
+    /// authenticate again with the same address and credentials. Sessions opened from
+    /// an existing SSH session only open a new channel on it, so it must still be alive.
+    /// The welcome banner is awaited again and every subscribe command registered with
+    /// [TesiraSession::subscribe] is sent again with the same label, so subscription
+    /// channels keep receiving publish tokens.
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        let Some(ssh) = self.transport.as_ref().and_then(|it| it.ssh()) else {
+            // LIBSSH2_ERROR_SOCKET_DISCONNECT
+            return Err(ssh2::Error::new(
+                ssh2::ErrorCode::Session(-13),
+                "No SSH session to reconnect",
+            )
+            .into());
+        };
+        let credentials = ssh.credentials.clone();
+        let session = match &credentials {
+            Some(credentials) => credentials.connect()?,
+            None => ssh.session.clone(),
+        };
+        let channel = open_ssh_shell(&session)?;
+
+        self.read_stream = BufReader::new(channel.clone());
+        self.write_stream = BufWriter::new(channel.clone());
+        self.read_buffer.clear();
+        self.pending_token.clear();
+        let mut transport = SshTransport {
+            session,
+            channel,
+            credentials,
+        };
+        transport.set_read_timeout(self.command_timeout)?;
+        self.transport = Some(Box::new(transport));
+
+        // Options the session was opened with still apply to the new channel
+        let options = self.options.clone();
+        self.wait_banner(&options)?;
+        self.replay_subscriptions()
+    }
+}
+
+/// Open a channel running the shell serving the text protocol
+#[cfg(feature = "ssh")]
+fn open_ssh_shell(session: &ssh2::Session) -> Result<ssh2::Channel, Error> {
+    let mut channel = session.channel_session()?;
+    channel.request_pty("ansi", None, None)?;
+    channel.shell()?;
+    Ok(channel)
+}
+
+impl TesiraSession<TcpStream, TcpStream> {
+    /// Communicate with tesira over a raw TCP stream
+    ///
+    /// No Telnet negotiation is performed, the stream must carry raw protocol lines
+    pub fn new_from_tcp(stream: TcpStream) -> Result<Self, Error> {
+        let transport = stream.try_clone()?;
+        let mut new_self = Self::new_from_stream(stream.try_clone()?, stream)?;
+        new_self.transport = Some(Box::new(transport));
+        Ok(new_self)
+    }
+}
+
+#[cfg(feature = "telnet")]
+impl TesiraSession<telnet::TelnetStream<TcpStream>, TcpStream> {
+    /// Connect to tesira device over Telnet
+    ///
+    /// Telnet options negotiated by device are refused
+    pub fn new_from_telnet(hostname: &str, port: u16) -> Result<Self, Error> {
+        let stream = TcpStream::connect((hostname, port))?;
+        let transport = stream.try_clone()?;
+        let mut new_self =
+            Self::new_from_stream(telnet::TelnetStream::new(stream.try_clone()?), stream)?;
+        new_self.transport = Some(Box::new(transport));
+        Ok(new_self)
+    }
+
+    /// Connect to tesira device over Telnet given a `host[:port]` string
+    ///
+    /// Port 23 is used when `hostname` has no `:port` suffix, IPv6 addresses with a port
+    /// must be enclosed in brackets such as `[::1]:23`.
+    pub fn new_from_telnet_host(hostname: &str) -> Result<Self, Error> {
+        let (host, port) = split_host_port(hostname, 23)?;
+        Self::new_from_telnet(host, port)
     }
 }
 
 impl<R: Read, W: Write> TesiraSession<R, W> {
     /// Create a new session from arbitrary read and write stream
     ///
-    /// See [TesiraSession::new_from_ssh] to use ssh
-    pub fn new_from_stream(read_strea: R, write_stream: W) -> Result<Self, Error> {
+    /// See [TesiraSession::new_from_ssh] to use ssh or [TesiraSession::new_from_tcp]
+    pub fn new_from_stream(read_stream: R, write_stream: W) -> Result<Self, Error> {
+        Self::new_from_stream_with_options(read_stream, write_stream, SessionOptions::default())
+    }
+
+    /// Create a new session from arbitrary read and write stream with custom options
+    pub fn new_from_stream_with_options(
+        read_stream: R,
+        write_stream: W,
+        options: SessionOptions,
+    ) -> Result<Self, Error> {
         let mut new_self = Self {
-            read_stream: BufReader::new(read_strea),
-            write_stream,
+            read_stream: BufReader::new(read_stream),
+            write_stream: BufWriter::new(write_stream),
+            read_buffer: String::new(),
+            last_echo: None,
             pending_token: VecDeque::new(),
+            current_values: HashMap::new(),
+            subscriptions: HashMap::new(),
+            subscribe_commands: HashMap::new(),
+            multiline_errors: false,
+            options: options.clone(),
+            command_timeout: None,
+            keepalive: None,
+            last_sent: Instant::now(),
+            stats: SessionStats::default(),
+            transport: None,
         };
+        new_self.wait_banner(&options)?;
+        Ok(new_self)
+    }
+
+    fn wait_banner(&mut self, options: &SessionOptions) -> Result<(), Error> {
         let mut banner_buffer = String::new();
-        while !banner_buffer.starts_with("Welcome") {
-            // Wait for welcome line
+        let mut line_count = 0;
+        let mut byte_count = 0;
+        loop {
+            if line_count >= options.banner_max_lines || byte_count >= options.banner_max_bytes {
+                return Err(Error::BannerNotFound);
+            }
+            // Wait for welcome line, skipping blank lines
             banner_buffer.clear();
-            new_self.read_stream.read_line(&mut banner_buffer)?;
+            let byte_red = (&mut self.read_stream)
+                .take((options.banner_max_bytes - byte_count) as u64)
+                .read_line(&mut banner_buffer)?;
+            if byte_red == 0 {
+                return Err(Error::UnexpectedEnd);
+            }
+            line_count += 1;
+            byte_count += byte_red;
+            if banner_buffer.trim().is_empty() {
+                continue;
+            }
+            if banner_buffer.starts_with("Welcome") {
+                return Ok(());
+            }
         }
-        Ok(new_self)
+    }
+
+    /// Snapshot of counters of commands and responses since session was opened
+    pub fn stats(&self) -> SessionStats {
+        self.stats
+    }
+
+    /// Accumulate indented lines following an error response into its message
+    ///
+    /// Only continuation lines already received with the error line are gathered,
+    /// so that reading never blocks waiting for lines that may never come.
+    /// Disabled by default.
+    pub fn set_multiline_errors(&mut self, enabled: bool) {
+        self.multiline_errors = enabled;
+    }
+
+    /// Maximum time [TesiraSession::send_command] waits for a response, `None` waits forever
+    ///
+    /// When exceeded, [Error::Timeout] is returned.
+    /// SSH sessions map it to the SSH session timeout, other streams must return
+    /// [io::ErrorKind::WouldBlock] or [io::ErrorKind::TimedOut] on reads to be interrupted,
+    /// such as a [std::net::TcpStream] with a read timeout.
+    pub fn set_command_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Error> {
+        if let Some(transport) = self.transport.as_mut() {
+            transport.set_read_timeout(timeout)?;
+        }
+        self.command_timeout = timeout;
+        Ok(())
+    }
+
+    /// Idle time after which [TesiraSession::keepalive] pokes the device, `None` disables it
+    ///
+    /// Idle SSH sessions are dropped by devices after a few minutes without traffic.
+    /// No background thread is spawned: it would have to share the session behind a lock
+    /// and its responses would interleave with commands of the caller. Instead call
+    /// [TesiraSession::keepalive] on your own cadence, such as between received tokens,
+    /// it only sends a command once the session has been idle long enough.
+    pub fn set_keepalive(&mut self, interval: Option<Duration>) {
+        self.keepalive = interval;
+    }
+
+    /// Poke the device if no command was sent during the keepalive interval
+    ///
+    /// Returns whether device was poked, see [TesiraSession::set_keepalive]
+    pub fn keepalive(&mut self) -> Result<bool, Error> {
+        match self.keepalive {
+            Some(interval) if self.last_sent.elapsed() >= interval => {
+                self.poke()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Send a harmless command to keep the session alive, its response is discarded
+    ///
+    /// Any response proves the channel is alive, so an error response is not an error here,
+    /// unlike [TesiraSession::ping]. Only failures of the session itself are returned.
+    pub fn poke(&mut self) -> Result<(), Error> {
+        match self.ping() {
+            Ok(()) | Err(Error::CommandFailed { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check device answers commands with a read without side effects
+    ///
+    /// Any `+OK` response is a success. An [Error::CommandFailed] means device is reachable
+    /// but refused the read, other errors mean the session itself is broken.
+    pub fn ping(&mut self) -> Result<(), Error> {
+        self.send_command(Command::builder().device().version())?;
+        Ok(())
+    }
+
+    /// Reboot device
+    ///
+    /// Device drops the connection while rebooting, so an [Error::UnexpectedEnd] is a success.
+    /// The session is unusable afterward, drop it and open a new one once device is back.
+    pub fn reboot(&mut self) -> Result<(), Error> {
+        match self.send_command(Command::builder().device().reboot()) {
+            Ok(_) | Err(Error::UnexpectedEnd) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send commands written but still buffered
+    ///
+    /// Commands are flushed before awaiting their responses, so this only matters
+    /// after a write was interrupted by an error.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.write_stream.flush()?;
+        Ok(())
+    }
+
+    /// End the session, flushing pending writes and closing the underlying transport
+    ///
+    /// SSH channels are closed and TCP connections shut down,
+    /// sessions over arbitrary streams are flushed and dropped.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.write_stream.flush()?;
+        if let Some(transport) = self.transport.as_mut() {
+            transport.close()?;
+        }
+        Ok(())
+    }
+
+    /// Log out from device then end the session, see [TesiraSession::close]
+    ///
+    /// A "bye" line is sent without awaiting any response, device ends the session on its side.
+    pub fn close_with_logout(mut self) -> Result<(), Error> {
+        self.write_lines([proto::commands::COMMAND_BYE])?;
+        self.close()
+    }
+
+    /// Get back the buffered read stream and the write stream
+    ///
+    /// Bytes received but not processed yet are left in the read buffer,
+    /// see [TesiraSession::pending_tokens] for publish tokens queued by the session.
+    /// Every command is flushed once sent, so no written byte is left behind.
+    pub fn into_inner(self) -> (BufReader<R>, W) {
+        (self.read_stream, self.write_stream.into_parts().0)
+    }
+
+    /// Split session into a writer sending commands and a reader receiving publish tokens
+    ///
+    /// See [split] module documentation.
+    pub fn split(self) -> (TesiraWriter<W>, TesiraReader<R>) {
+        let Self {
+            read_stream,
+            write_stream,
+            read_buffer,
+            last_echo,
+            pending_token,
+            current_values,
+            subscriptions,
+            subscribe_commands,
+            multiline_errors,
+            options,
+            command_timeout,
+            keepalive,
+            last_sent,
+            stats,
+            transport,
+        } = self;
+        (
+            TesiraWriter::new(write_stream),
+            TesiraReader::new(TesiraSession {
+                read_stream,
+                write_stream: BufWriter::new(io::sink()),
+                read_buffer,
+                last_echo,
+                pending_token,
+                current_values,
+                subscriptions,
+                subscribe_commands,
+                multiline_errors,
+                options,
+                command_timeout,
+                keepalive,
+                last_sent,
+                stats,
+                transport,
+            }),
+        )
     }
 
     /// Get all available aliases
@@ -100,6 +616,123 @@ impl<R: Read, W: Write> TesiraSession<R, W> {
         }
     }
 
+    /// Send a get command expecting a number value
+    pub fn get_f64<'b>(&mut self, cmd: impl Into<Command<'b>>) -> Result<f64, Error> {
+        match self.send_command(cmd)? {
+            OkResponse::WithValue(Value::Number(v)) => Ok(v),
+            OkResponse::WithValue(Value::Integer(v)) => Ok(v as f64),
+            response => Err(Error::UnexpectedResponse(
+                Response::Ok(response),
+                "a response with a number value".to_owned(),
+            )),
+        }
+    }
+
+    /// Send a get command expecting a boolean value
+    pub fn get_bool<'b>(&mut self, cmd: impl Into<Command<'b>>) -> Result<bool, Error> {
+        match self.send_command(cmd)? {
+            OkResponse::WithValue(Value::Boolean(v)) => Ok(v),
+            response => Err(Error::UnexpectedResponse(
+                Response::Ok(response),
+                "a response with a boolean value".to_owned(),
+            )),
+        }
+    }
+
+    /// Send a get command expecting a string value, constants are returned as strings
+    pub fn get_string<'b>(&mut self, cmd: impl Into<Command<'b>>) -> Result<String, Error> {
+        match self.send_command(cmd)? {
+            OkResponse::WithValue(Value::String(v) | Value::Constant(v)) => Ok(v),
+            response => Err(Error::UnexpectedResponse(
+                Response::Ok(response),
+                "a response with a string value".to_owned(),
+            )),
+        }
+    }
+
+    /// Read all bands of a parametric equalizer block
+    ///
+    /// Bands are read one attribute at a time, so this sends three commands per band
+    pub fn get_eq_curve(
+        &mut self,
+        instance_tag: impl Into<InstanceTag>,
+    ) -> Result<Vec<EqBand>, Error> {
+        let eq = Command::builder().parametric_equalizer(instance_tag);
+        let band_count: f64 = self.get_value(eq.numbands())?;
+
+        (1..=band_count as u64)
+            .map(|band| {
+                let freq_gain: Value = self.get_value(eq.frequencygain(band))?;
+                Ok(EqBand {
+                    freq_gain: FreqGain::try_from(freq_gain)?,
+                    bandwidth: Some(self.get_value(eq.bandwidth(band))?),
+                    bypass: self.get_value(eq.bypass(band))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Read all bands of a graphic equalizer block
+    ///
+    /// Center frequencies are fixed by the band count, see
+    /// [GraphicEqualizerCommandBuilder::center_frequency]. Two commands are sent per band.
+    pub fn get_graphic_eq_curve(
+        &mut self,
+        instance_tag: impl Into<InstanceTag>,
+    ) -> Result<Vec<EqBand>, Error> {
+        let eq = Command::builder().graphic_equalizer(instance_tag);
+        let band_count = self.get_value::<f64>(eq.numbands())? as u64;
+
+        (1..=band_count)
+            .map(|band| {
+                let frequency = GraphicEqualizerCommandBuilder::center_frequency(band_count, band)
+                    .ok_or_else(|| {
+                        proto::Error::InvalidValue(format!("unknown band count {band_count}"))
+                    })?;
+                Ok(EqBand {
+                    freq_gain: FreqGain {
+                        frequency,
+                        gain: self.get_value(eq.gain(band))?,
+                    },
+                    bandwidth: None,
+                    bypass: self.get_value(eq.bypass(band))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Read network status of device
+    pub fn get_network_status(&mut self) -> Result<DeviceNetworkStatus, Error> {
+        self.get_value(Command::builder().device().networkstatus())
+    }
+
+    /// Read firmware version of device, such as `4.2.0.24`
+    pub fn firmware_version(&mut self) -> Result<String, Error> {
+        self.get_value(Command::builder().device().version())
+    }
+
+    /// Read serial number of device
+    pub fn serial_number(&mut self) -> Result<String, Error> {
+        self.get_value(Command::builder().device().serialnumber())
+    }
+
+    /// Read active faults of device, an empty list means device reports no fault
+    pub fn get_faults(&mut self) -> Result<Vec<FaultStatus>, Error> {
+        let value: Value = self.get_value(Command::builder().device().activefaultlist())?;
+        Ok(parse_fault_list(value)?)
+    }
+
+    /// Send a command expecting a value of a given type in response
+    fn get_value<'b, T: FromTTP>(&mut self, cmd: impl Into<Command<'b>>) -> Result<T, Error> {
+        match self.send_command(cmd)? {
+            OkResponse::WithValue(value) => Ok(T::from_ttp_value(value)?),
+            response => Err(Error::UnexpectedResponse(
+                Response::Ok(response),
+                "a response with a value".to_owned(),
+            )),
+        }
+    }
+
     /// Send direct command and await for a response from device
     ///
     /// See [TesiraSession::set], [TesiraSession::get], [TesiraSession::get_aliases] or [TesiraSession::subscribe]
@@ -108,10 +741,71 @@ impl<R: Read, W: Write> TesiraSession<R, W> {
         cmd: impl Into<Command<'b>>,
     ) -> Result<OkResponse, Error> {
         let command: Command = cmd.into();
-        let cmd_str = format!("{}\n", command.into_ttp());
-        self.write_stream.write_all(cmd_str.as_bytes())?;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("send_command", command = %command).entered();
+        // Command is written without building its line, echo is checked against it as well
+        command.write_ttp(&mut self.write_stream)?;
+        self.stats.commands_sent += 1;
+        #[cfg(feature = "tracing")]
+        tracing::trace!(line = %command, "sent");
+        self.flush_lines()?;
+        self.recv_command_response(Some(&command))
+            .map_err(|e| command_failed(e, &command))
+    }
+
+    /// Send an already formatted command line and await for a response from device
+    ///
+    /// Line is kept to check the echo and attach it to [Error::CommandFailed].
+    ///
+    /// Device echoes the command line before its response, a response following the echo
+    /// of another command is not attributed to this command. Missing echoes are tolerated.
+    fn send_line(&mut self, line: &str) -> Result<OkResponse, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("send_command", command = line).entered();
+        self.write_lines([line])?;
+        self.recv_command_response(Some(&line))
+            .map_err(|e| command_failed(e, &line))
+    }
+
+    /// Write command lines and flush them at once, without waiting for their responses
+    fn write_lines<'l>(&mut self, lines: impl IntoIterator<Item = &'l str>) -> Result<(), Error> {
+        for line in lines {
+            self.write_stream.write_all(line.as_bytes())?;
+            self.write_stream.write_all(b"\n")?;
+            self.stats.commands_sent += 1;
+            #[cfg(feature = "tracing")]
+            tracing::trace!(line, "sent");
+        }
+        self.flush_lines()
+    }
+
+    /// Flush lines written, responses read from now on follow their echoes
+    fn flush_lines(&mut self) -> Result<(), Error> {
+        self.write_stream.flush()?;
+        self.last_echo = None;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    /// Await for the response to a command line already sent
+    ///
+    /// The echo is checked against `line` when given, a response following the echo of another
+    /// command is returned in [Error::UnexpectedResponse]. Pipelined commands can't be checked
+    /// as echoes of following commands may come before the response.
+    fn recv_command_response(&mut self, line: Option<&dyn Display>) -> Result<OkResponse, Error> {
+        let deadline = self.command_timeout.map(|it| Instant::now() + it);
         loop {
-            let response = self.recv_response()?;
+            let response = self.recv_response(deadline)?;
+            if matches!(response, Response::Ok(_) | Response::Err(_))
+                && let Some(echo) = self.last_echo.take()
+                && let Some(line) = line
+                && !is_echo_of(&echo, line)
+            {
+                return Err(Error::UnexpectedResponse(
+                    response,
+                    format!("response after echo {echo:?}, expected echo of {line}"),
+                ));
+            }
             match response {
                 Response::Err(e) => return Err(Error::OperationFailed(e)),
                 Response::Ok(res) => return Ok(res),
@@ -120,30 +814,331 @@ impl<R: Read, W: Write> TesiraSession<R, W> {
         }
     }
 
-    fn recv_response(&mut self) -> Result<Response, Error> {
-        let mut buf = String::new();
+    /// Send many commands at once and get their responses in the same order
+    ///
+    /// Every command is written before awaiting responses, saving a round trip per command.
+    /// Device processes commands in the order they are received, so responses are matched
+    /// to commands by position. Publish tokens received meanwhile are queued for
+    /// [TesiraSession::recv_token]. Failed operations are returned per command,
+    /// other errors abort the batch.
+    pub fn send_batch<'b>(
+        &mut self,
+        cmds: Vec<Command<'b>>,
+    ) -> Result<Vec<Result<OkResponse, ErrResponse>>, Error> {
+        let lines = cmds.into_iter().map(|it| it.into_ttp()).collect::<Vec<_>>();
+        self.write_lines(lines.iter().map(String::as_str))?;
+
+        (0..lines.len())
+            .map(|_| match self.recv_command_response(None) {
+                Ok(response) => Ok(Ok(response)),
+                Err(Error::OperationFailed(e)) => Ok(Err(e)),
+                Err(e) => Err(e),
+            })
+            .collect()
+    }
+
+    /// Send a command, sending it again with exponential backoff on transient errors
+    ///
+    /// Only error responses accepted by [RetryPolicy::retriable] are retried,
+    /// other errors are returned immediately.
+    pub fn send_command_retry<'b>(
+        &mut self,
+        cmd: impl Into<Command<'b>>,
+        policy: RetryPolicy,
+    ) -> Result<OkResponse, Error> {
+        let line = cmd.into().into_ttp();
+        let mut delay = policy.base_delay;
+        let mut attempt = 1;
+        loop {
+            match self.send_line(&line) {
+                Err(Error::CommandFailed { response, .. })
+                    if attempt < policy.max_attempts && (policy.retriable)(&response) =>
+                {
+                    thread::sleep(delay);
+                    delay = delay.mul_f64(policy.multiplier);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send a subscribe command and get a channel receiving its publish tokens
+    ///
+    /// Publish tokens are routed to the returned receiver by [TesiraSession::dispatch_next_token].
+    /// The command must be a "subscribe" command with a subscription label as first value,
+    /// such as built by [Command::new_subscribe] or generated `subscribe_*` builders.
+    pub fn subscribe<'a, 'b: 'a>(
+        &'a mut self,
+        cmd: impl Into<Command<'b>>,
+    ) -> Result<Receiver<PublishToken>, Error> {
+        let command: Command = cmd.into();
+        let label = subscription_label(&command, proto::commands::COMMAND_SUBSCRIBE)
+            .ok_or_else(|| Error::InvalidSubscription(command.clone().into_ttp()))?;
+
+        let line = command.into_ttp();
+        self.send_line(&line)?;
+        Ok(self.register_subscription(label, line))
+    }
+
+    /// Send many subscribe commands at once and get a channel per command, in the same order
+    ///
+    /// Every command is written before awaiting responses, saving a round trip per subscription.
+    /// Publish tokens received meanwhile are queued for [TesiraSession::recv_token].
+    /// Each command gets its own result, so subscriptions accepted by device keep their
+    /// receiver when another one is refused with [Error::CommandFailed].
+    /// Invalid commands fail the whole call before anything is sent, as do I/O errors.
+    /// See [TesiraSession::subscribe] for requirements on commands.
+    pub fn subscribe_many<'b>(
+        &mut self,
+        cmds: impl IntoIterator<Item = Command<'b>>,
+    ) -> Result<Vec<Result<Receiver<PublishToken>, Error>>, Error> {
+        let subscriptions = cmds
+            .into_iter()
+            .map(|command| {
+                let label = subscription_label(&command, proto::commands::COMMAND_SUBSCRIBE)
+                    .ok_or_else(|| Error::InvalidSubscription(command.clone().into_ttp()))?;
+                Ok((label, command.into_ttp()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        self.write_lines(subscriptions.iter().map(|(_, line)| line.as_str()))?;
+
+        let mut receivers = Vec::with_capacity(subscriptions.len());
+        for (label, line) in subscriptions {
+            match self.recv_command_response(None) {
+                Ok(_) => receivers.push(Ok(self.register_subscription(label, line))),
+                Err(e @ Error::OperationFailed(_)) => {
+                    receivers.push(Err(command_failed(e, &line.as_str())))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(receivers)
+    }
+
+    fn register_subscription(&mut self, label: String, line: String) -> Receiver<PublishToken> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscriptions.insert(label.clone(), sender);
+        self.subscribe_commands.insert(label, line);
+        receiver
+    }
+
+    /// Send an unsubscribe command and close the channel of its subscription
+    ///
+    /// The command must be an "unsubscribe" command with a subscription label as first value,
+    /// such as built by [Command::new_unsubscribe] or generated `unsubscribe_*` builders.
+    /// The receiver returned by [TesiraSession::subscribe] is disconnected once device accepted it.
+    pub fn unsubscribe<'a, 'b: 'a>(&'a mut self, cmd: impl Into<Command<'b>>) -> Result<(), Error> {
+        let command: Command = cmd.into();
+        let label = subscription_label(&command, proto::commands::COMMAND_UNSUBSCRIBE)
+            .ok_or_else(|| Error::InvalidSubscription(command.clone().into_ttp()))?;
+
+        self.send_command(command)?;
+
+        self.subscriptions.remove(&label);
+        self.subscribe_commands.remove(&label);
+        self.current_values.remove(&label);
+        Ok(())
+    }
+
+    /// Send again every subscribe command registered with [TesiraSession::subscribe]
+    #[cfg_attr(not(feature = "ssh"), allow(dead_code))]
+    fn replay_subscriptions(&mut self) -> Result<(), Error> {
+        let lines = self
+            .subscribe_commands
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for line in lines {
+            self.send_line(&line)?;
+        }
+        Ok(())
+    }
+
+    /// Await for the next publish token and send it to the channel of its subscription
+    ///
+    /// Returns [Error::UnknownSubscription] if the token label was not registered
+    /// with [TesiraSession::subscribe]. A subscription whose receiver was dropped is forgotten.
+    pub fn dispatch_next_token(&mut self) -> Result<(), Error> {
+        let token = self.recv_token()?;
+        self.dispatch_token(token)
+    }
+
+    fn dispatch_token(&mut self, token: PublishToken) -> Result<(), Error> {
+        let Some(sender) = self.subscriptions.get(&token.label) else {
+            return Err(Error::UnknownSubscription(token.label));
+        };
+        if let Err(mpsc::SendError(token)) = sender.send(token) {
+            self.subscriptions.remove(&token.label);
+        }
+        Ok(())
+    }
+
+    fn recv_response(&mut self, deadline: Option<Instant>) -> Result<Response, Error> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("recv_response", kind = tracing::field::Empty).entered();
+        // Buffer is kept between calls so steady state reads do not allocate
+        self.read_buffer.clear();
         loop {
             // Ignore empty lines
-            let byte_red = self.read_stream.read_line(&mut buf)?;
+            let remaining = self
+                .options
+                .max_response_len
+                .saturating_sub(self.read_buffer.len());
+            let byte_red = match (&mut self.read_stream)
+                .take(remaining as u64)
+                .read_line(&mut self.read_buffer)
+            {
+                Ok(byte_red) => byte_red,
+                Err(e)
+                    if self.command_timeout.is_some()
+                        && matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    // Read timed out, partial line is kept in buffer
+                    if deadline.is_some_and(|it| Instant::now() >= it) {
+                        return Err(Error::Timeout);
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            check_response_len(&mut self.read_buffer, self.options.max_response_len)?;
             if byte_red == 0 {
                 return Err(Error::UnexpectedEnd);
             }
+            trim_carriage_return(&mut self.read_buffer);
 
-            let trim_buf = buf.trim();
+            let trim_buf = self.read_buffer.trim();
+            #[cfg(feature = "tracing")]
+            tracing::trace!(line = trim_buf, "received");
             if !trim_buf.is_empty()
                 && (&trim_buf[0..1] == "-" || &trim_buf[0..1] == "+" || &trim_buf[0..1] == "!")
             {
-                return Ok(Response::parse_ttp(&buf)?);
+                if self.multiline_errors && &trim_buf[0..1] == "-" {
+                    while matches!(self.read_stream.buffer().first(), Some(b' ' | b'\t')) {
+                        let remaining = self
+                            .options
+                            .max_response_len
+                            .saturating_sub(self.read_buffer.len());
+                        (&mut self.read_stream)
+                            .take(remaining as u64)
+                            .read_line(&mut self.read_buffer)?;
+                        check_response_len(&mut self.read_buffer, self.options.max_response_len)?;
+                        trim_carriage_return(&mut self.read_buffer);
+                    }
+                }
+                let response = match Response::parse_ttp(&self.read_buffer) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        self.stats.parse_failures += 1;
+                        return Err(e.into());
+                    }
+                };
+                match &response {
+                    Response::Ok(_) => self.stats.ok_responses += 1,
+                    Response::Err(_) => self.stats.err_responses += 1,
+                    Response::PublishToken(token) => {
+                        self.stats.publish_tokens += 1;
+                        self.update_current_value(token);
+                    }
+                }
+                #[cfg(feature = "tracing")]
+                span.record(
+                    "kind",
+                    match &response {
+                        Response::Ok(_) => "ok",
+                        Response::Err(_) => "err",
+                        Response::PublishToken(_) => "publish_token",
+                    },
+                );
+                return Ok(response);
             } else {
-                buf.clear();
+                if !trim_buf.is_empty() {
+                    // Command echoed by device
+                    self.last_echo = Some(trim_buf.to_owned());
+                }
+                self.read_buffer.clear();
             }
         }
     }
 
-    /// Await for a publish token to come
-    ///
-    /// Please prefer usage of [TesiraSession::subscribe] and [TesiraSession::dispatch_next_token]
-    /// and use channels to receive PublishToken in a multithreaded environment
+    fn update_current_value(&mut self, token: &PublishToken) {
+        match self.current_values.get_mut(&token.label) {
+            Some(current) => current.deep_merge(token.value.clone()),
+            None => {
+                self.current_values
+                    .insert(token.label.clone(), token.value.clone());
+            }
+        }
+    }
+
+    /// Current value of a subscription, coalesced from every received publish token
+    ///
+    /// Devices may publish only the changed part of structured values,
+    /// those partial updates are merged with [Value::deep_merge].
+    /// Returns `None` if no publish token was received for this subscription label.
+    pub fn current_value(&self, label: &str) -> Option<&Value> {
+        self.current_values.get(label)
+    }
+
+    /// Iterate over incoming publish tokens
+    ///
+    /// Pending tokens are yielded first, then tokens are read from device until stream ends.
+    /// Iteration stops after yielding the first error.
+    pub fn tokens(&mut self) -> impl Iterator<Item = Result<PublishToken, Error>> + '_ {
+        let mut ended = false;
+        iter::from_fn(move || {
+            if ended {
+                return None;
+            }
+            match self.recv_token() {
+                Ok(token) => Some(Ok(token)),
+                Err(Error::UnexpectedEnd) => {
+                    ended = true;
+                    None
+                }
+                Err(e) => {
+                    ended = true;
+                    Some(Err(e))
+                }
+            }
+        })
+    }
+
+    /// Record every incoming numeric publish token in a metric sink until stream ends
+    ///
+    /// Boolean values are recorded as 0 or 1, other values such as strings or arrays are skipped.
+    pub fn dispatch_to_metrics(&mut self, sink: &mut impl MetricSink) -> Result<(), Error> {
+        for token in self.tokens() {
+            let token = token?;
+            if let Some(value) = metrics::metric_value(&token) {
+                sink.record(&token.label, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish tokens received while awaiting command responses, in arrival order
+    pub fn pending_tokens(&self) -> impl Iterator<Item = &PublishToken> {
+        self.pending_token.iter().rev()
+    }
+
+    /// Take every publish token received while awaiting command responses, in arrival order
+    ///
+    /// This does not read from device, tokens not yet received are left to [TesiraSession::recv_token].
+    pub fn drain_pending_tokens(&mut self) -> Vec<PublishToken> {
+        // Tokens are pushed at front, oldest is at back
+        self.pending_token.drain(..).rev().collect()
+    }
+
+    /// Await for a publish token to come
+    ///
+    /// Please prefer usage of [TesiraSession::subscribe] and [TesiraSession::dispatch_next_token]
+    /// and use channels to receive PublishToken in a multithreaded environment
     ///
     /// Use this method if you subscribed manually and wants to get all Publish tokens in one thread
     pub fn recv_token(&mut self) -> Result<PublishToken, Error> {
@@ -151,7 +1146,7 @@ impl<R: Read, W: Write> TesiraSession<R, W> {
             return Ok(pending_token);
         }
 
-        let response = self.recv_response()?;
+        let response = self.recv_response(None)?;
         match response {
             Response::PublishToken(t) => Ok(t),
             r @ (Response::Err(_) | Response::Ok(_)) => {
@@ -161,33 +1156,200 @@ impl<R: Read, W: Write> TesiraSession<R, W> {
     }
 }
 
+/// Attach the command line to an error response, other errors are returned unchanged
+fn command_failed(error: Error, line: &dyn Display) -> Error {
+    match error {
+        Error::OperationFailed(response) => Error::CommandFailed {
+            command: line.to_string().trim().to_owned(),
+            response,
+        },
+        e => e,
+    }
+}
+
+/// Check an echo received from device is the given line, ignoring trailing whitespace
+///
+/// Line is compared while being formatted, so commands are not written to a string.
+fn is_echo_of(echo: &str, line: &dyn Display) -> bool {
+    struct EchoMatcher<'e> {
+        rest: &'e str,
+        matches: bool,
+    }
+
+    impl fmt::Write for EchoMatcher<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if let Some(rest) = self.rest.strip_prefix(s) {
+                self.rest = rest;
+            } else if let Some(tail) = s.strip_prefix(self.rest)
+                && tail.trim_end().is_empty()
+            {
+                self.rest = "";
+            } else {
+                self.matches = false;
+            }
+            Ok(())
+        }
+    }
+
+    let echo = echo.trim();
+    let mut matcher = EchoMatcher {
+        rest: echo,
+        matches: true,
+    };
+    let _ = fmt::write(&mut matcher, format_args!("{line}"));
+    matcher.matches && matcher.rest.is_empty()
+}
+
+/// Split an optional `:port` suffix from a host, IPv6 addresses are accepted with or without brackets
+#[cfg_attr(not(any(feature = "ssh", feature = "telnet")), allow(dead_code))]
+fn split_host_port(address: &str, default_port: u16) -> Result<(&str, u16), Error> {
+    let invalid_port = || io::Error::new(io::ErrorKind::InvalidInput, "Invalid port number");
+    if let Some(bracketed) = address.strip_prefix('[') {
+        let (host, rest) = bracketed.split_once(']').ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Unclosed IPv6 address bracket")
+        })?;
+        return match rest.strip_prefix(':') {
+            Some(port) => Ok((host, port.parse().map_err(|_| invalid_port())?)),
+            None if rest.is_empty() => Ok((host, default_port)),
+            None => Err(invalid_port().into()),
+        };
+    }
+    match address.split_once(':') {
+        // More than one colon is a bare IPv6 address
+        Some((host, port)) if !port.contains(':') => {
+            Ok((host, port.parse().map_err(|_| invalid_port())?))
+        }
+        _ => Ok((address, default_port)),
+    }
+}
+
+/// Turn a trailing CRLF line ending, as sent over Telnet, into LF
+pub(crate) fn trim_carriage_return(line: &mut String) {
+    if line.ends_with("\r\n") {
+        line.truncate(line.len() - 2);
+        line.push('\n');
+    }
+}
+
+/// Fail when a line filled the buffer up to maximum length without being terminated
+///
+/// Buffer is cleared so the oversized line does not stay in memory, the rest of the line
+/// is still pending in the stream.
+pub(crate) fn check_response_len(
+    buffer: &mut String,
+    max_response_len: usize,
+) -> Result<(), Error> {
+    if buffer.len() >= max_response_len && !buffer.ends_with('\n') {
+        buffer.clear();
+        return Err(Error::ResponseTooLarge(max_response_len));
+    }
+    Ok(())
+}
+
+/// Label of a subscribe or unsubscribe command, as echoed back by device in publish tokens
+fn subscription_label(command: &Command, expected_command: &str) -> Option<String> {
+    if command.command != expected_command {
+        return None;
+    }
+    command
+        .values
+        .first()
+        .map(|label| label.trim_matches('"').to_owned())
+        .filter(|label| !label.is_empty())
+}
+
 /// Error that can occur when interacting with Tesira sessions
-#[derive(Debug, Error)]
+///
+/// IO and SSH errors are shared so that errors can be cloned,
+/// they are compared by kind and message.
+#[derive(Debug, Clone, Error)]
 pub enum Error {
     /// IO Error on streams
     #[error("IO Error : {0}")]
-    IO(#[from] io::Error),
-    /// Received an Error response
+    IO(#[source] Arc<io::Error>),
+    /// Received an Error response that could not be attributed to a command
+    ///
+    /// Failures of sent commands are returned as [Error::CommandFailed].
     #[error("Operation failed on device : {0}")]
     OperationFailed(ErrResponse),
+    /// Received an Error response to a command
+    #[error("Operation failed on device : {command} → {response}")]
+    CommandFailed {
+        /// Command line that failed, without line terminator
+        command: String,
+        /// Error response of device
+        response: ErrResponse,
+    },
     /// Failed to parse response send by device
     #[error("Response parsing failed : {0}")]
-    ParsingFailed(String),
+    ParsingFailed(#[from] proto::Error),
     /// Response sent by device wasn't expected
     #[error("Unexpected response from device: {0:?} (expected {1})")]
     UnexpectedResponse(Response, String),
+    /// A response line exceeds [SessionOptions::max_response_len], carried in bytes
+    #[error("Response exceeds maximum length of {0} bytes")]
+    ResponseTooLarge(usize),
     /// Stream ends before end of response
     #[error("Unexpected end of read stream")]
     UnexpectedEnd,
+    /// Welcome banner was not received within limits of [SessionOptions]
+    #[error("Welcome banner not found")]
+    BannerNotFound,
+    /// Device did not respond in time
+    #[error("Timed out waiting for device response")]
+    Timeout,
+    /// Command is not a valid subscription
+    #[error("Invalid subscription command: {0}")]
+    InvalidSubscription(String),
+    /// Received a publish token of a subscription not registered in session
+    #[error("Unknown subscription: {0}")]
+    UnknownSubscription(String),
     #[cfg(feature = "ssh")]
     #[error("SSH error: {0}")]
     /// SSH error
-    Ssh(#[from] ssh2::Error),
+    Ssh(#[source] Arc<ssh2::Error>),
 }
 
-impl<'a> From<proto::Error<'a>> for Error {
-    fn from(value: proto::Error) -> Self {
-        Self::ParsingFailed(format!("{value}"))
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::IO(Arc::new(value))
+    }
+}
+
+#[cfg(feature = "ssh")]
+impl From<ssh2::Error> for Error {
+    fn from(value: ssh2::Error) -> Self {
+        Self::Ssh(Arc::new(value))
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::IO(a), Self::IO(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            (Self::OperationFailed(a), Self::OperationFailed(b)) => a == b,
+            (
+                Self::CommandFailed {
+                    command: a_command,
+                    response: a,
+                },
+                Self::CommandFailed {
+                    command: b_command,
+                    response: b,
+                },
+            ) => a_command == b_command && a == b,
+            (Self::ParsingFailed(a), Self::ParsingFailed(b)) => a == b,
+            (Self::UnexpectedResponse(a, a_expected), Self::UnexpectedResponse(b, b_expected)) => {
+                a == b && a_expected == b_expected
+            }
+            (Self::ResponseTooLarge(a), Self::ResponseTooLarge(b)) => a == b,
+            (Self::InvalidSubscription(a), Self::InvalidSubscription(b))
+            | (Self::UnknownSubscription(a), Self::UnknownSubscription(b)) => a == b,
+            #[cfg(feature = "ssh")]
+            (Self::Ssh(a), Self::Ssh(b)) => a.code() == b.code() && a.message() == b.message(),
+            // Remaining variants carry no data
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
     }
 }
 
@@ -196,154 +1358,1229 @@ mod test {
     use std::{
         cell::LazyCell,
         collections::HashSet,
-        io::{BufReader, BufWriter, Cursor, Write},
+        io::{self, BufReader, BufWriter, Cursor, Read, Write},
+        thread,
+        time::Duration,
     };
 
-    #[allow(unused_imports)]
-    use crate::{
-        Error, TesiraSession,
-        proto::{Command, ErrResponse, OkResponse, PublishToken, Value},
-    };
+    #[allow(unused_imports)]
+    use crate::{
+        EqBand, Error, RetryPolicy, SessionOptions, SessionStats, TesiraSession,
+        builder::FreqGain,
+        is_echo_of,
+        metrics::InMemorySink,
+        proto::{
+            Command, ErrResponse, OkResponse, PublishToken, Response, Value, ValueMap,
+            fault::FaultSeverity,
+        },
+        split_host_port,
+    };
+
+    #[allow(dead_code)]
+    fn welcome_banner() -> Vec<u8> {
+        "Welcome to the Tesira Text Protocol Server...\n\n"
+            .as_bytes()
+            .to_vec()
+    }
+
+    #[test]
+    fn should_default_port_when_missing() {
+        assert_eq!(split_host_port("host", 22).unwrap(), ("host", 22));
+        assert_eq!(split_host_port("host:2222", 22).unwrap(), ("host", 2222));
+        assert_eq!(split_host_port("[::1]:22", 23).unwrap(), ("::1", 22));
+        assert_eq!(split_host_port("[::1]", 23).unwrap(), ("::1", 23));
+        assert_eq!(split_host_port("fe80::1", 22).unwrap(), ("fe80::1", 22));
+        assert!(split_host_port("host:ssh", 22).is_err());
+        assert!(split_host_port("[::1", 22).is_err());
+    }
+
+    #[test]
+    fn should_match_echo_with_command() {
+        let command = Command::new_get("Level3", "level", [1]);
+        assert!(is_echo_of("Level3 get level 1", &command));
+        assert!(is_echo_of("Level3 get level 1 ", &command));
+        assert!(!is_echo_of("Level3 get level 12", &command));
+        assert!(!is_echo_of("Level3 get level", &command));
+        assert!(is_echo_of("Level3 set label 1", &"Level3 set label 1 "));
+    }
+
+    #[test]
+    fn should_fail_on_end_before_banner() {
+        let session = TesiraSession::new_from_stream(Cursor::new(Vec::new()), Vec::new());
+        assert!(matches!(session, Err(Error::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn should_fail_on_end_after_unexpected_banner() {
+        let input = "Tesira Server Login\nPassword:\n".as_bytes().to_vec();
+        let session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new());
+        assert!(matches!(session, Err(Error::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn should_stop_looking_for_banner_after_limits() {
+        let input = "garbage\n".repeat(10).into_bytes();
+        let session = TesiraSession::new_from_stream_with_options(
+            Cursor::new(input),
+            Vec::new(),
+            SessionOptions {
+                banner_max_lines: 5,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(session, Err(Error::BannerNotFound)));
+
+        let input = "garbage without end of line ".repeat(10).into_bytes();
+        let session = TesiraSession::new_from_stream_with_options(
+            Cursor::new(input),
+            Vec::new(),
+            SessionOptions {
+                banner_max_bytes: 100,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(session, Err(Error::BannerNotFound)));
+
+        let mut input = "garbage\n".repeat(4).into_bytes();
+        input.extend_from_slice(&welcome_banner());
+        let session = TesiraSession::new_from_stream_with_options(
+            Cursor::new(input),
+            Vec::new(),
+            SessionOptions {
+                banner_max_lines: 5,
+                ..Default::default()
+            },
+        );
+        // Options are kept to wait for the banner again on reconnection
+        assert_eq!(session.unwrap().options.banner_max_lines, 5);
+    }
+
+    #[test]
+    fn should_fail_on_response_too_large() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 get label 1\n+OK \"value\":\"".as_bytes());
+        input.extend_from_slice("x".repeat(100).as_bytes());
+        input.extend_from_slice("\"\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream_with_options(
+            Cursor::new(input),
+            Vec::new(),
+            SessionOptions {
+                max_response_len: 64,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            session.send_command(Command::new_get("Level3", "label", [1])),
+            Err(Error::ResponseTooLarge(64))
+        );
+        assert!(session.read_buffer.is_empty());
+    }
+
+    #[test]
+    fn should_skip_blank_lines_before_banner() {
+        let mut input = "\n\r\n  \n".as_bytes().to_vec();
+        input.extend_from_slice(&welcome_banner());
+        input.extend_from_slice("Level3 get level 2\n+OK \"value\":0.000000\n".as_bytes());
+
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+        assert_eq!(
+            session
+                .send_command(Command::new_get("Level3", "level", [2]))
+                .unwrap(),
+            OkResponse::WithValue(Value::Number(0.0))
+        );
+    }
+
+    #[test]
+    fn should_handle_crlf_line_endings() {
+        let mut input = "Welcome to the Tesira Text Protocol Server...\r\n\r\n"
+            .as_bytes()
+            .to_vec();
+        input.extend_from_slice("Level3 get level 2\r\n+OK \"value\":0.000000\r\n".as_bytes());
+        input.extend_from_slice("Level3 get label 2\r\n-ERR address not found\r\n".as_bytes());
+        input.extend_from_slice("  in Level3\r\n".as_bytes());
+
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+        session.set_multiline_errors(true);
+        assert_eq!(
+            session
+                .send_command(Command::new_get("Level3", "level", [2]))
+                .unwrap(),
+            OkResponse::WithValue(Value::Number(0.0))
+        );
+
+        let response = session.send_command(Command::new_get("Level3", "label", [2]));
+        if let Err(Error::CommandFailed { response: e, .. }) = response {
+            assert_eq!(e.message, "address not found\n  in Level3");
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+    }
+
+    #[test]
+    fn should_handle_valid_set_command() {
+        let write_c = Cursor::new(Vec::new());
+        let read_c = Cursor::new(welcome_banner());
+        let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
+
+        session
+            .read_stream
+            .get_mut()
+            .get_mut()
+            .extend_from_slice("Level3 set level 2 0\n".as_bytes()); // Should also handle echo
+        session
+            .read_stream
+            .get_mut()
+            .get_mut()
+            .extend_from_slice("+OK\n".as_bytes());
+        session
+            .send_command(Command::new_set("Level3", "level", [2], 0))
+            .unwrap();
+
+        assert_eq!(
+            *session.write_stream.get_ref().get_ref(),
+            "Level3 set level 2 0\n".as_bytes().to_vec()
+        );
+    }
+
+    #[test]
+    fn should_handle_valid_get_command() {
+        let write_c = Cursor::new(Vec::new());
+        let read_c = Cursor::new(welcome_banner());
+
+        let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
+
+        session
+            .read_stream
+            .get_mut()
+            .get_mut()
+            .extend_from_slice("Level3 get level 2\n".as_bytes()); // Should also handle echo
+        session
+            .read_stream
+            .get_mut()
+            .get_mut()
+            .extend_from_slice("+OK \"value\":0.000000\n".as_bytes());
+        let response = session
+            .send_command(Command::new_get("Level3", "level", [2]))
+            .unwrap();
+
+        assert_eq!(
+            *session.write_stream.get_ref().get_ref(),
+            "Level3 get level 2\n".as_bytes().to_vec()
+        );
+        assert_eq!(response, OkResponse::WithValue(Value::Number(0.0)));
+    }
+
+    #[test]
+    fn should_get_typed_values() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK \"value\":-10.500000\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":true\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":\"Lobby\"\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":true\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":-10.500000\n".as_bytes());
+        input.extend_from_slice("+OK\n".as_bytes());
+
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+        assert_eq!(
+            session
+                .get_f64(Command::new_get("Level3", "level", [1]))
+                .unwrap(),
+            -10.5
+        );
+        assert!(
+            session
+                .get_bool(Command::new_get("Level3", "mute", [1]))
+                .unwrap()
+        );
+        assert_eq!(
+            session
+                .get_string(Command::new_get("DEVICE", "hostname", []))
+                .unwrap(),
+            "Lobby"
+        );
+
+        assert!(matches!(
+            session.get_f64(Command::new_get("Level3", "mute", [1])),
+            Err(Error::UnexpectedResponse(_, _))
+        ));
+        assert!(matches!(
+            session.get_bool(Command::new_get("Level3", "level", [1])),
+            Err(Error::UnexpectedResponse(_, _))
+        ));
+        assert!(matches!(
+            session.get_string(Command::new_get("Level3", "level", [1])),
+            Err(Error::UnexpectedResponse(_, _))
+        ));
+    }
+
+    #[test]
+    fn should_reject_response_following_another_echo() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 get level 1\n+OK \"value\":-10.000000\n".as_bytes());
+        input.extend_from_slice("Level3 get mute 1\n+OK \"value\":true\n".as_bytes());
+        input.extend_from_slice("\x1b[0mLevel3 get level 2\n+OK \"value\":-8.000000\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert_eq!(
+            session
+                .send_command(Command::new_get("Level3", "level", [1]))
+                .unwrap(),
+            OkResponse::WithValue(Value::Number(-10.0))
+        );
+        // Response of a command sent by another client interleaved with ours
+        let response = session.send_command(Command::new_get("Level3", "level", [2]));
+        if let Err(Error::UnexpectedResponse(response, context)) = response {
+            assert_eq!(
+                response,
+                Response::Ok(OkResponse::WithValue(Value::Boolean(true)))
+            );
+            assert_eq!(
+                context,
+                "response after echo \"Level3 get mute 1\", expected echo of Level3 get level 2"
+            );
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+        // A decorated echo is reported instead of waiting for another response
+        let response = session.send_command(Command::new_get("Level3", "level", [2]));
+        assert!(matches!(
+            response,
+            Err(Error::UnexpectedResponse(Response::Ok(OkResponse::WithValue(Value::Number(v))), _)) if v == -8.0
+        ));
+    }
+
+    #[test]
+    fn should_poke_device() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE get version\n+OK \"value\":\"4.2.0.24\"\n".as_bytes());
+        input.extend_from_slice("DEVICE get version\n-ERR device busy\n".as_bytes());
+        input.extend_from_slice("Level3 get mute 1\n+OK \"value\":true\n".as_bytes());
+        input.extend_from_slice("DEVICE get version\n+OK \"value\":\"4.2.0.24\"\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        session.poke().unwrap();
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get version\n".as_bytes()
+        );
+        assert!(session.pending_tokens().next().is_none());
+
+        // An error response still proves device is alive, and is consumed
+        session.write_stream.get_mut().clear();
+        session.poke().unwrap();
+        assert_eq!(session.stats().commands_sent, 2);
+        assert_eq!(
+            session
+                .send_command(Command::new_get("Level3", "mute", [1]))
+                .unwrap(),
+            OkResponse::WithValue(Value::Boolean(true))
+        );
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get version\nLevel3 get mute 1\n".as_bytes()
+        );
+
+        session.write_stream.get_mut().clear();
+        assert!(!session.keepalive().unwrap());
+        session.set_keepalive(Some(Duration::from_secs(60)));
+        assert!(!session.keepalive().unwrap());
+        session.set_keepalive(Some(Duration::ZERO));
+        assert!(session.keepalive().unwrap());
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get version\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn should_ping_device() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE get version\n+OK \"value\":\"4.2.0.24\"\n".as_bytes());
+        input.extend_from_slice("DEVICE get version\n-ERR device busy\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert_eq!(session.ping(), Ok(()));
+        assert_eq!(
+            session.ping(),
+            Err(Error::CommandFailed {
+                command: "DEVICE get version".to_owned(),
+                response: ErrResponse::new("device busy"),
+            })
+        );
+        assert_eq!(session.ping(), Err(Error::UnexpectedEnd));
+    }
+
+    #[test]
+    fn should_reboot_device() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE reboot\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert_eq!(session.reboot(), Ok(()));
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE reboot\n".as_bytes()
+        );
+
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE reboot\n-ERR not allowed\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert!(matches!(session.reboot(), Err(Error::CommandFailed { .. })));
+    }
+
+    #[test]
+    fn should_handle_valid_get_aliases_command() {
+        let write_c = Cursor::new(Vec::new());
+        let read_c = Cursor::new(welcome_banner());
+
+        let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
+
+        session
+            .read_stream
+            .get_mut()
+            .get_mut()
+            .extend_from_slice("SESSION get aliases\n".as_bytes()); // Should also handle echo
+        session.read_stream.get_mut().get_mut().extend_from_slice("+OK \"list\":[\"AecInput1\" \"AudioMeter2\" \"AudioMeter4\" \"DEVICE\" \"DanteInput1\" \"DanteOutput1\" \"Level1\" \"Level2\" \"Level3\" \"Mixer1\" \"NoiseGenerator1\" \"Output1\" \"Router1\" \"ToneGenerator1\" \"ToneGenerator2\" \"USBInput1\" \"USBOutput1\"]\n".as_bytes());
+        let response = session.get_aliases().unwrap();
+
+        assert_eq!(
+            *session.write_stream.get_ref().get_ref(),
+            "SESSION get aliases\n".as_bytes().to_vec()
+        );
+        assert_eq!(
+            response,
+            HashSet::from([
+                "AecInput1".to_owned(),
+                "AudioMeter2".to_owned(),
+                "AudioMeter4".to_owned(),
+                "DEVICE".to_owned(),
+                "DanteInput1".to_owned(),
+                "DanteOutput1".to_owned(),
+                "Level1".to_owned(),
+                "Level2".to_owned(),
+                "Level3".to_owned(),
+                "Mixer1".to_owned(),
+                "NoiseGenerator1".to_owned(),
+                "Output1".to_owned(),
+                "Router1".to_owned(),
+                "ToneGenerator1".to_owned(),
+                "ToneGenerator2".to_owned(),
+                "USBInput1".to_owned(),
+                "USBOutput1".to_owned()
+            ])
+        );
+    }
+
+    #[test]
+    fn should_handle_failed_operation() {
+        let write_c = Cursor::new(Vec::new());
+        let read_c = Cursor::new(welcome_banner());
+
+        let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
+
+        session
+            .read_stream
+            .get_mut()
+            .get_mut()
+            .extend_from_slice("Level3 set mute 3 true\n".as_bytes()); // Should also handle echo
+        session.read_stream.get_mut().get_mut().extend_from_slice(
+            "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}\n"
+                .as_bytes(),
+        );
+        let response = session.send_command(Command::new_set("Level3", "mute", [3], true));
+
+        assert_eq!(
+            *session.write_stream.get_ref().get_ref(),
+            "Level3 set mute 3 true\n".as_bytes().to_vec()
+        );
+
+        if let Err(e @ Error::CommandFailed { .. }) = &response {
+            assert_eq!(
+                e,
+                &Error::CommandFailed {
+                    command: "Level3 set mute 3 true".to_owned(),
+                    response: ErrResponse::new(
+                        "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
+                    ),
+                }
+            );
+            assert_eq!(
+                e.to_string(),
+                "Operation failed on device : Level3 set mute 3 true → address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
+            );
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+    }
+
+    #[test]
+    fn should_compare_and_clone_errors() {
+        let failed = Error::OperationFailed(ErrResponse::new("address not found"));
+        assert_eq!(failed.clone(), failed);
+        assert_ne!(failed, Error::UnexpectedEnd);
+        assert_eq!(Error::UnexpectedEnd, Error::UnexpectedEnd);
+
+        let io_error = Error::from(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+        assert_eq!(io_error.clone(), io_error);
+        assert_ne!(
+            io_error,
+            Error::from(io::Error::new(io::ErrorKind::BrokenPipe, "reset"))
+        );
+
+        let mut session =
+            TesiraSession::new_from_stream(Cursor::new(welcome_banner()), Vec::new()).unwrap();
+        assert_eq!(
+            session.send_command(Command::new_get("Level3", "level", [1])),
+            Err(Error::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn should_count_session_stats() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "+OK\n! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n-ERR address not found\n! garbage\n"
+                .as_bytes(),
+        );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+        assert_eq!(session.stats(), SessionStats::default());
+
+        session
+            .send_command(Command::new_set("Level3", "mute", [1], true))
+            .unwrap();
+        assert!(
+            session
+                .send_command(Command::new_set("Level4", "mute", [1], true))
+                .is_err()
+        );
+        assert!(
+            session
+                .send_command(Command::new_get("Level3", "level", [1]))
+                .is_err()
+        );
+        assert_eq!(
+            session.stats(),
+            SessionStats {
+                commands_sent: 3,
+                ok_responses: 1,
+                err_responses: 1,
+                publish_tokens: 1,
+                parse_failures: 1,
+            }
+        );
+    }
+
+    #[allow(dead_code)]
+    #[derive(Default)]
+    struct CountingWriter {
+        written: Vec<u8>,
+        writes: usize,
+        flushes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_write_batch_at_once() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK\n+OK\n+OK\n".as_bytes());
+        let mut session =
+            TesiraSession::new_from_stream(Cursor::new(input), CountingWriter::default()).unwrap();
+
+        session
+            .send_batch(vec![
+                Command::new_set("Level3", "mute", [1], true),
+                Command::new_set("Level3", "mute", [2], true),
+            ])
+            .unwrap();
+        let writer = session.write_stream.get_ref();
+        assert_eq!(
+            writer.written,
+            "Level3 set mute 1 true\nLevel3 set mute 2 true\n".as_bytes()
+        );
+        assert_eq!((writer.writes, writer.flushes), (1, 1));
+
+        session
+            .send_command(Command::new_set("Level3", "mute", [3], true))
+            .unwrap();
+        let writer = session.write_stream.get_ref();
+        assert_eq!((writer.writes, writer.flushes), (2, 2));
+
+        session.flush().unwrap();
+        assert_eq!(session.write_stream.get_ref().flushes, 3);
+    }
+
+    #[test]
+    fn should_send_batch_in_order() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "Level3 set mute 1 true\nLevel3 get level 1\nLevel4 set mute 1 true\n".as_bytes(),
+        );
+        input.extend_from_slice(
+            "+OK\n! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n+OK \"value\":-10.000000\n-ERR address not found\n"
+                .as_bytes(),
+        );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let responses = session
+            .send_batch(vec![
+                Command::new_set("Level3", "mute", [1], true),
+                Command::new_get("Level3", "level", [1]),
+                Command::new_set("Level4", "mute", [1], true),
+            ])
+            .unwrap();
+        assert_eq!(
+            responses,
+            vec![
+                Ok(OkResponse::Ok),
+                Ok(OkResponse::WithValue(Value::Number(-10.0))),
+                Err(ErrResponse::new("address not found"))
+            ]
+        );
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "Level3 set mute 1 true\nLevel3 get level 1\nLevel4 set mute 1 true\n".as_bytes()
+        );
+        assert_eq!(
+            session.recv_token().unwrap(),
+            PublishToken {
+                label: "MyLevel".to_owned(),
+                value: Value::Number(-10.0)
+            }
+        );
+    }
+
+    #[test]
+    fn should_retry_transient_failures() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("-ERR busy\n-ERR busy\n+OK\n".as_bytes());
+        input.extend_from_slice("-ERR busy\n-ERR address not found\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let policy = RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..RetryPolicy::new(|e| e.message == "busy")
+        };
+        assert_eq!(
+            session
+                .send_command_retry(
+                    Command::new_set("Level3", "mute", [1], true),
+                    policy.clone()
+                )
+                .unwrap(),
+            OkResponse::Ok
+        );
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "Level3 set mute 1 true\n".repeat(3).as_bytes()
+        );
+
+        session.write_stream.get_mut().clear();
+        let response =
+            session.send_command_retry(Command::new_set("Level4", "mute", [1], true), policy);
+        if let Err(Error::CommandFailed { response: e, .. }) = response {
+            assert_eq!(e.message, "address not found");
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "Level4 set mute 1 true\n".repeat(2).as_bytes()
+        );
+    }
+
+    #[test]
+    fn should_give_up_retrying_after_max_attempts() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("-ERR busy\n-ERR busy\n+OK\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
 
-    #[allow(dead_code)]
-    fn welcome_banner() -> Vec<u8> {
-        "Welcome to the Tesira Text Protocol Server...\n\n"
-            .as_bytes()
-            .to_vec()
+        let policy = RetryPolicy {
+            base_delay: Duration::ZERO,
+            max_attempts: 2,
+            ..RetryPolicy::new(|_| true)
+        };
+        assert!(matches!(
+            session.send_command_retry(Command::new_set("Level3", "mute", [1], true), policy),
+            Err(Error::CommandFailed { .. })
+        ));
     }
 
     #[test]
-    fn should_handle_valid_set_command() {
+    fn should_handle_multiline_failed_operation() {
         let write_c = Cursor::new(Vec::new());
         let read_c = Cursor::new(welcome_banner());
+
         let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
+        session.set_multiline_errors(true);
 
         session
             .read_stream
             .get_mut()
             .get_mut()
-            .extend_from_slice("Level3 set level 2 0\n".as_bytes()); // Should also handle echo
+            .extend_from_slice("DEVICE set recallPreset 1001\n".as_bytes()); // Should also handle echo
         session
             .read_stream
             .get_mut()
             .get_mut()
-            .extend_from_slice("+OK\n".as_bytes());
-        session
-            .send_command(Command::new_set("Level3", "level", [2], 0))
-            .unwrap();
+            .extend_from_slice("-ERR cannot recall preset\n  preset 1001 not found\n".as_bytes());
+        let response = session.send_command(Command::new_set("DEVICE", "recallPreset", [], 1001));
+
+        if let Err(Error::CommandFailed { response: e, .. }) = response {
+            assert_eq!(
+                e,
+                ErrResponse::new("cannot recall preset\n  preset 1001 not found")
+            )
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+    }
+
+    #[test]
+    fn should_coalesce_partial_publishes() {
+        let write_c = Cursor::new(Vec::new());
+        let read_c = Cursor::new(welcome_banner());
+        let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
+
+        session.read_stream.get_mut().get_mut().extend_from_slice(
+            "! \"publishToken\":\"CallState\" \"value\":{\"state\":{\"line\":1 \"callState\":CONNECTED} \"callerId\":\"123\"}\n"
+                .as_bytes(),
+        );
+        session.read_stream.get_mut().get_mut().extend_from_slice(
+            "! \"publishToken\":\"CallState\" \"value\":{\"state\":{\"callState\":IDLE}}\n"
+                .as_bytes(),
+        );
+
+        assert_eq!(session.current_value("CallState"), None);
+        session.recv_token().unwrap();
+        session.recv_token().unwrap();
 
         assert_eq!(
-            session.write_stream.into_inner(),
-            "Level3 set level 2 0\n".as_bytes().to_vec()
+            session.current_value("CallState"),
+            Some(&Value::Map(ValueMap::from([
+                (
+                    "state".to_owned(),
+                    Value::Map(ValueMap::from([
+                        ("line".to_owned(), Value::Integer(1)),
+                        ("callState".to_owned(), Value::Constant("IDLE".to_owned())),
+                    ])),
+                ),
+                ("callerId".to_owned(), Value::String("123".to_owned())),
+            ])))
         );
     }
 
     #[test]
-    fn should_handle_valid_get_command() {
+    fn should_dispatch_tokens_to_subscription() {
         let write_c = Cursor::new(Vec::new());
         let read_c = Cursor::new(welcome_banner());
-
         let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
 
         session
             .read_stream
             .get_mut()
             .get_mut()
-            .extend_from_slice("Level3 get level 2\n".as_bytes()); // Should also handle echo
+            .extend_from_slice("Level3 subscribe level 1 MyLevel\n+OK\n".as_bytes());
+        let receiver = session
+            .subscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+            .unwrap();
+
+        session.read_stream.get_mut().get_mut().extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n! \"publishToken\":\"Other\" \"value\":true\n"
+                .as_bytes(),
+        );
+        session.dispatch_next_token().unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            PublishToken {
+                label: "MyLevel".to_owned(),
+                value: Value::Number(-10.0)
+            }
+        );
+
+        let response = session.dispatch_next_token();
+        if let Err(Error::UnknownSubscription(label)) = response {
+            assert_eq!(label, "Other");
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+    }
+
+    #[test]
+    fn should_close_subscription_on_unsubscribe() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK\n+OK\n".as_bytes());
+        input.extend_from_slice("! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let receiver = session
+            .subscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+            .unwrap();
         session
-            .read_stream
-            .get_mut()
-            .get_mut()
-            .extend_from_slice("+OK \"value\":0.000000\n".as_bytes());
-        let response = session
-            .send_command(Command::new_get("Level3", "level", [2]))
+            .unsubscribe(Command::new_unsubscribe("Level3", "level", [1], "MyLevel"))
+            .unwrap();
+        assert!(matches!(receiver.recv(), Err(std::sync::mpsc::RecvError)));
+
+        let response = session.dispatch_next_token();
+        if let Err(Error::UnknownSubscription(label)) = response {
+            assert_eq!(label, "MyLevel");
+        } else {
+            panic!("Unexpected response : {response:?}")
+        }
+
+        assert!(matches!(
+            session.unsubscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel")),
+            Err(Error::InvalidSubscription(_))
+        ));
+    }
+
+    #[test]
+    fn should_replay_subscriptions() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("+OK\n+OK\n+OK\n+OK\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let _level = session
+            .subscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
             .unwrap();
+        let _mute = session
+            .subscribe(Command::new_subscribe("Level3", "mute", [1], "MyMute"))
+            .unwrap();
+        session
+            .unsubscribe(Command::new_unsubscribe("Level3", "mute", [1], "MyMute"))
+            .unwrap();
+        session.write_stream.get_mut().clear();
 
+        session.replay_subscriptions().unwrap();
         assert_eq!(
-            session.write_stream.into_inner(),
-            "Level3 get level 2\n".as_bytes().to_vec()
+            *session.write_stream.get_ref(),
+            "Level3 subscribe level 1 MyLevel\n".as_bytes()
         );
-        assert_eq!(response, OkResponse::WithValue(Value::Number(0.0)));
     }
 
     #[test]
-    fn should_handle_valid_get_aliases_command() {
+    fn should_get_network_status() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE get networkStatus\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":{\"hostname\":\"TesiraForte05953601\" \"defaultGatewayStatus\":\"10.0.148.1\" \"networkInterfaceStatusWithName\":[] \"dnsStatus\":{\"primaryDNSServer\":\"10.0.148.1\" \"secondaryDNSServer\":\"10.0.148.2\" \"domainName\":\"local\"} \"mDNSEnabled\":false \"telnetDisabled\":false \"sshDisabled\":false \"futureFlag\":true}\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let status = session.get_network_status().unwrap();
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get networkStatus\n".as_bytes()
+        );
+        assert_eq!(status.hostname, "TesiraForte05953601");
+        assert_eq!(status.dns_servers, ["10.0.148.1", "10.0.148.2"]);
+        assert_eq!(status.domain_name, "local");
+    }
+
+    #[test]
+    fn should_get_firmware_version() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE get version\n+OK \"value\":\"4.2.0.24\"\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert_eq!(session.firmware_version().unwrap(), "4.2.0.24");
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get version\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn should_get_serial_number() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE get serialNumber\n+OK \"value\":\"05953601\"\n".as_bytes());
+        input.extend_from_slice("DEVICE get serialNumber\n+OK\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert_eq!(session.serial_number().unwrap(), "05953601");
+        assert!(matches!(
+            session.serial_number(),
+            Err(Error::UnexpectedResponse(..))
+        ));
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get serialNumber\nDEVICE get serialNumber\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn should_get_faults() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("DEVICE get activeFaultList\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":[{\"id\":INDICATOR_MINOR_IN_DEVICE \"name\":\"Minor Fault in Device\" \"faults\":[{\"id\":FAULT_NETWORK_LINK \"name\":\"network link down\"}]}]\n".as_bytes());
+        input.extend_from_slice("DEVICE get activeFaultList\n".as_bytes());
+        input.extend_from_slice("+OK \"value\":[{\"id\":INDICATOR_NONE_IN_DEVICE \"name\":\"No fault in device\" \"faults\":[]}]\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let faults = session.get_faults().unwrap();
+        assert_eq!(faults.len(), 1);
+        assert_eq!(faults[0].id, "FAULT_NETWORK_LINK");
+        assert_eq!(faults[0].severity, FaultSeverity::Minor);
+        assert!(session.get_faults().unwrap().is_empty());
+        assert_eq!(
+            *session.write_stream.get_ref(),
+            "DEVICE get activeFaultList\nDEVICE get activeFaultList\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn should_get_eq_curve() {
+        let mut input = welcome_banner();
+        for line in [
+            "PEQ1 get numBands",
+            "+OK \"value\":2",
+            "PEQ1 get frequencyGain 1",
+            "+OK \"value\":{\"frequency\":100.000000 \"gain\":-3.500000}",
+            "PEQ1 get bandwidth 1",
+            "+OK \"value\":1.000000",
+            "PEQ1 get bypass 1",
+            "+OK \"value\":false",
+            "PEQ1 get frequencyGain 2",
+            "+OK \"value\":{\"frequency\":2500.000000 \"gain\":6.000000}",
+            "PEQ1 get bandwidth 2",
+            "+OK \"value\":0.500000",
+            "PEQ1 get bypass 2",
+            "+OK \"value\":true",
+        ] {
+            input.extend_from_slice(format!("{line}\n").as_bytes());
+        }
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        assert_eq!(
+            session.get_eq_curve("PEQ1").unwrap(),
+            vec![
+                EqBand {
+                    freq_gain: FreqGain {
+                        frequency: 100.0,
+                        gain: -3.5
+                    },
+                    bandwidth: Some(1.0),
+                    bypass: false
+                },
+                EqBand {
+                    freq_gain: FreqGain {
+                        frequency: 2500.0,
+                        gain: 6.0
+                    },
+                    bandwidth: Some(0.5),
+                    bypass: true
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn should_get_graphic_eq_curve() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("GEQ1 get numBands\n+OK \"value\":10\n".as_bytes());
+        for band in 1..=10 {
+            input.extend_from_slice(
+                format!("+OK \"value\":{band}.000000\n+OK \"value\":false\n").as_bytes(),
+            );
+        }
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let curve = session.get_graphic_eq_curve("GEQ1").unwrap();
+        assert_eq!(curve.len(), 10);
+        assert!((curve[0].freq_gain.frequency - 31.62).abs() < 0.01);
+        assert!((curve[9].freq_gain.frequency - 15848.93).abs() < 0.01);
+        assert_eq!(curve[9].freq_gain.gain, 10.0);
+        assert_eq!(curve[9].bandwidth, None);
+    }
+
+    #[test]
+    fn should_receive_subscription_tokens_on_channel() {
         let write_c = Cursor::new(Vec::new());
         let read_c = Cursor::new(welcome_banner());
-
         let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
 
         session
             .read_stream
             .get_mut()
             .get_mut()
-            .extend_from_slice("SESSION get aliases\n".as_bytes()); // Should also handle echo
-        session.read_stream.get_mut().get_mut().extend_from_slice("+OK \"list\":[\"AecInput1\" \"AudioMeter2\" \"AudioMeter4\" \"DEVICE\" \"DanteInput1\" \"DanteOutput1\" \"Level1\" \"Level2\" \"Level3\" \"Mixer1\" \"NoiseGenerator1\" \"Output1\" \"Router1\" \"ToneGenerator1\" \"ToneGenerator2\" \"USBInput1\" \"USBOutput1\"]\n".as_bytes());
-        let response = session.get_aliases().unwrap();
+            .extend_from_slice("AudioMeter1 subscribe level 1 MyMeter 100\n+OK\n".as_bytes());
+        let receiver = session
+            .subscribe(
+                Command::builder()
+                    .audio_meter("AudioMeter1")
+                    .subscribe_level_with_rate(1, "MyMeter", Duration::from_millis(100)),
+            )
+            .unwrap();
+
+        session.read_stream.get_mut().get_mut().extend_from_slice(
+            "! \"publishToken\":\"MyMeter\" \"value\":-40.000000\n! \"publishToken\":\"MyMeter\" \"value\":-38.500000\n"
+                .as_bytes(),
+        );
+        session.dispatch_next_token().unwrap();
+        session.dispatch_next_token().unwrap();
 
         assert_eq!(
-            session.write_stream.into_inner(),
-            "SESSION get aliases\n".as_bytes().to_vec()
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                PublishToken {
+                    label: "MyMeter".to_owned(),
+                    value: Value::Number(-40.0)
+                },
+                PublishToken {
+                    label: "MyMeter".to_owned(),
+                    value: Value::Number(-38.5)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn should_subscribe_many_at_once() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "Level3 subscribe level 1 Level1\n+OK\n! \"publishToken\":\"Level1\" \"value\":-10.000000\nLevel3 subscribe level 2 Level2\n+OK\n"
+                .as_bytes(),
+        );
+        input.extend_from_slice(
+            "! \"publishToken\":\"Level2\" \"value\":-8.000000\n! \"publishToken\":\"Level1\" \"value\":-6.000000\n"
+                .as_bytes(),
         );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let receivers = session
+            .subscribe_many([
+                Command::new_subscribe("Level3", "level", [1], "Level1"),
+                Command::new_subscribe("Level3", "level", [2], "Level2"),
+            ])
+            .unwrap()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
         assert_eq!(
-            response,
-            HashSet::from([
-                "AecInput1".to_owned(),
-                "AudioMeter2".to_owned(),
-                "AudioMeter4".to_owned(),
-                "DEVICE".to_owned(),
-                "DanteInput1".to_owned(),
-                "DanteOutput1".to_owned(),
-                "Level1".to_owned(),
-                "Level2".to_owned(),
-                "Level3".to_owned(),
-                "Mixer1".to_owned(),
-                "NoiseGenerator1".to_owned(),
-                "Output1".to_owned(),
-                "Router1".to_owned(),
-                "ToneGenerator1".to_owned(),
-                "ToneGenerator2".to_owned(),
-                "USBInput1".to_owned(),
-                "USBOutput1".to_owned()
+            *session.write_stream.get_ref(),
+            "Level3 subscribe level 1 Level1\nLevel3 subscribe level 2 Level2\n".as_bytes()
+        );
+
+        for _ in 0..3 {
+            session.dispatch_next_token().unwrap();
+        }
+        assert_eq!(
+            receivers[0]
+                .try_iter()
+                .map(|it| it.value)
+                .collect::<Vec<_>>(),
+            vec![Value::Number(-10.0), Value::Number(-6.0)]
+        );
+        assert_eq!(
+            receivers[1]
+                .try_iter()
+                .map(|it| it.value)
+                .collect::<Vec<_>>(),
+            vec![Value::Number(-8.0)]
+        );
+    }
+
+    #[test]
+    fn should_read_every_response_when_one_subscription_fails() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("-ERR address not found\n+OK\n+OK\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let mut receivers = session
+            .subscribe_many([
+                Command::new_subscribe("Level4", "level", [1], "Level1"),
+                Command::new_subscribe("Level3", "level", [2], "Level2"),
             ])
+            .unwrap();
+        assert!(matches!(
+            receivers.remove(0),
+            Err(Error::CommandFailed { command, .. }) if command == "Level4 subscribe level 1 Level1"
+        ));
+        assert!(receivers.remove(0).is_ok());
+        assert!(session.subscriptions.contains_key("Level2"));
+        assert!(!session.subscriptions.contains_key("Level1"));
+        // Stream is still in sync with commands
+        assert_eq!(
+            session
+                .send_command(Command::new_set("Level3", "mute", [1], true))
+                .unwrap(),
+            OkResponse::Ok
         );
     }
 
     #[test]
-    fn should_handle_failed_operation() {
+    fn should_reject_subscription_without_label() {
         let write_c = Cursor::new(Vec::new());
         let read_c = Cursor::new(welcome_banner());
-
         let mut session = TesiraSession::new_from_stream(read_c, write_c).unwrap();
 
+        let response = session.subscribe(Command::new_get("Level3", "level", [1]));
+        assert!(
+            matches!(response, Err(Error::InvalidSubscription(_))),
+            "Unexpected response : {response:?}"
+        );
+        assert!(session.write_stream.get_ref().get_ref().is_empty());
+    }
+
+    /// Stream that stalls once its data is consumed, as a socket with a read timeout
+    #[allow(dead_code)]
+    struct StalledStream(Cursor<Vec<u8>>);
+
+    impl Read for StalledStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let byte_red = self.0.read(buf)?;
+            if byte_red == 0 {
+                thread::sleep(Duration::from_millis(1));
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            Ok(byte_red)
+        }
+    }
+
+    #[test]
+    fn should_timeout_on_missing_response() {
+        let mut input = welcome_banner();
+        input.extend_from_slice("Level3 get level 2\n+OK \"value\":0.0".as_bytes()); // Never terminated
+        let mut session =
+            TesiraSession::new_from_stream(StalledStream(Cursor::new(input)), Vec::new()).unwrap();
         session
-            .read_stream
-            .get_mut()
-            .get_mut()
-            .extend_from_slice("Level3 set mute 3 true\n".as_bytes()); // Should also handle echo
-        session.read_stream.get_mut().get_mut().extend_from_slice(
-            "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}\n"
+            .set_command_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+
+        let response = session.send_command(Command::new_get("Level3", "level", [2]));
+        assert!(
+            matches!(response, Err(Error::Timeout)),
+            "Unexpected response : {response:?}"
+        );
+    }
+
+    #[test]
+    fn should_drain_pending_tokens_in_arrival_order() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n! \"publishToken\":\"MyLevel\" \"value\":-8.000000\n+OK\n"
                 .as_bytes(),
         );
-        let response = session.send_command(Command::new_set("Level3", "mute", [3], true));
+        input.extend_from_slice("! \"publishToken\":\"MyMute\" \"value\":true\n+OK\n".as_bytes());
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        session
+            .send_command(Command::new_set("Level3", "level", [1], -8.0))
+            .unwrap();
+        session
+            .send_command(Command::new_set("Level3", "mute", [1], true))
+            .unwrap();
 
         assert_eq!(
-            session.write_stream.into_inner(),
-            "Level3 set mute 3 true\n".as_bytes().to_vec()
+            session
+                .drain_pending_tokens()
+                .into_iter()
+                .map(|it| it.value)
+                .collect::<Vec<_>>(),
+            vec![
+                Value::Number(-10.0),
+                Value::Number(-8.0),
+                Value::Boolean(true)
+            ]
         );
+        assert_eq!(session.drain_pending_tokens(), Vec::new());
+    }
 
-        if let Err(Error::OperationFailed(e)) = response {
-            assert_eq!(
-                e,
-                ErrResponse {
-                    message:
-                        "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
-                            .to_owned()
-                }
-            )
-        } else {
-            panic!("Unexpected response : {response:?}")
-        }
+    #[test]
+    fn should_give_back_streams() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n+OK\n+OK\n".as_bytes(),
+        );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        session
+            .send_command(Command::new_set("Level3", "level", [1], -10.0))
+            .unwrap();
+        assert_eq!(
+            session
+                .pending_tokens()
+                .map(|it| it.label.as_str())
+                .collect::<Vec<_>>(),
+            vec!["MyLevel"]
+        );
+
+        let (mut read_stream, write_stream) = session.into_inner();
+        assert_eq!(write_stream, "Level3 set level 1 -10\n".as_bytes());
+        let mut leftover = String::new();
+        read_stream.read_to_string(&mut leftover).unwrap();
+        assert_eq!(leftover, "+OK\n");
+    }
+
+    #[test]
+    fn should_dispatch_tokens_to_metrics() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n! \"publishToken\":\"MyMute\" \"value\":true\n! \"publishToken\":\"MyLabel\" \"value\":\"Mic\"\n! \"publishToken\":\"MyLevel\" \"value\":-6.000000\n"
+                .as_bytes(),
+        );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let mut sink = InMemorySink::default();
+        session.dispatch_to_metrics(&mut sink).unwrap();
+        assert_eq!(sink.get("MyLevel"), Some(-6.0));
+        assert_eq!(sink.get("MyMute"), Some(1.0));
+        assert_eq!(sink.get("MyLabel"), None);
+    }
+
+    #[test]
+    fn should_iterate_over_tokens_until_end() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n+OK\n".as_bytes(),
+        );
+        input.extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-8.000000\n! \"publishToken\":\"MyLevel\" \"value\":-6.000000\n"
+                .as_bytes(),
+        );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+        session
+            .send_command(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+            .unwrap();
+
+        assert_eq!(
+            session
+                .tokens()
+                .map(|it| it.unwrap().value)
+                .collect::<Vec<_>>(),
+            vec![
+                Value::Number(-10.0),
+                Value::Number(-8.0),
+                Value::Number(-6.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn should_stop_iterating_over_tokens_after_error() {
+        let mut input = welcome_banner();
+        input.extend_from_slice(
+            "! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n+OK\n! \"publishToken\":\"MyLevel\" \"value\":-8.000000\n"
+                .as_bytes(),
+        );
+        let mut session = TesiraSession::new_from_stream(Cursor::new(input), Vec::new()).unwrap();
+
+        let tokens = session.tokens().collect::<Vec<_>>();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens[0].is_ok());
+        assert!(matches!(tokens[1], Err(Error::UnexpectedResponse(_, _))));
     }
 
     #[test]
@@ -378,7 +2615,7 @@ mod test {
             .unwrap();
 
         assert_eq!(
-            *session.write_stream.get_ref(),
+            *session.write_stream.get_ref().get_ref(),
             "LogicMeter1 subscribe state 1 Subscription0\n"
                 .as_bytes()
                 .to_vec()
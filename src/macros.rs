@@ -0,0 +1,55 @@
+//! Macros to build commands
+
+/// Build a [Command](crate::Command) for any block and attribute
+///
+/// Syntax is `instance verb attribute [indexes...] => values...`, indexes and values are optional.
+/// Known verbs (`get`, `set`, `increment`, `decrement`, `toggle`, `subscribe`, `unsubscribe`)
+/// are written bare and checked at compile time, a string literal is accepted for custom verbs.
+/// Instance tag and attribute are literals or variables, wrap other expressions in parentheses.
+/// Values are converted with [IntoTTP](crate::proto::IntoTTP).
+///
+/// ```
+/// use tesira_text_protocol::{proto::IntoTTP, ttp_command};
+///
+/// let get = ttp_command!("Level1" get "level" [1]);
+/// assert_eq!(get.into_ttp(), "Level1 get level 1");
+///
+/// let set = ttp_command!("Level1" set "level" [1] => -10.0);
+/// assert_eq!(set.into_ttp(), "Level1 set level 1 -10");
+///
+/// let subscribe = ttp_command!("Meter1" subscribe "level" [1] => "MyMeter".to_owned(), 100);
+/// assert_eq!(subscribe.into_ttp(), "Meter1 subscribe level 1 MyMeter 100");
+///
+/// let custom = ttp_command!("Router1" "route" "input" [2] => 1);
+/// assert_eq!(custom.into_ttp(), "Router1 route input 2 1");
+/// ```
+///
+/// Misspelled verbs are rejected at compile time
+///
+/// ```compile_fail
+/// let get = tesira_text_protocol::ttp_command!("Level1" gett "level" [1]);
+/// ```
+#[macro_export]
+macro_rules! ttp_command {
+    (@verb get) => { $crate::proto::commands::COMMAND_GET };
+    (@verb set) => { $crate::proto::commands::COMMAND_SET };
+    (@verb increment) => { $crate::proto::commands::COMMAND_INCREMENT };
+    (@verb decrement) => { $crate::proto::commands::COMMAND_DECREMENT };
+    (@verb toggle) => { $crate::proto::commands::COMMAND_TOGGLE };
+    (@verb subscribe) => { $crate::proto::commands::COMMAND_SUBSCRIBE };
+    (@verb unsubscribe) => { $crate::proto::commands::COMMAND_UNSUBSCRIBE };
+    (@verb $verb:literal) => { $verb };
+    (
+        $instance:tt $verb:tt $attribute:tt
+        $([$($index:expr),* $(,)?])?
+        $(=> $($value:expr),+ $(,)?)?
+    ) => {
+        $crate::proto::Command {
+            instance_tag: ::core::convert::Into::into($instance),
+            command: $crate::ttp_command!(@verb $verb),
+            attribute: $attribute,
+            indexes: ::std::vec![$($($index),*)?],
+            values: ::std::vec![$($($crate::proto::IntoTTP::into_ttp($value)),+)?],
+        }
+    };
+}
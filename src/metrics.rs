@@ -0,0 +1,41 @@
+//! Export of publish tokens as metrics
+//!
+//! [TesiraSession::dispatch_to_metrics](crate::TesiraSession::dispatch_to_metrics) records
+//! numeric publish tokens in a [MetricSink], which can be backed by any metrics library.
+
+use std::collections::HashMap;
+
+use crate::proto::{PublishToken, Value};
+
+/// Destination of metrics recorded from publish tokens
+pub trait MetricSink {
+    /// Record latest value of a subscription label
+    fn record(&mut self, label: &str, value: f64);
+}
+
+/// Sink keeping latest value of each label in memory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InMemorySink(pub HashMap<String, f64>);
+
+impl InMemorySink {
+    /// Latest value recorded for a label
+    pub fn get(&self, label: &str) -> Option<f64> {
+        self.0.get(label).copied()
+    }
+}
+
+impl MetricSink for InMemorySink {
+    fn record(&mut self, label: &str, value: f64) {
+        self.0.insert(label.to_owned(), value);
+    }
+}
+
+/// Numeric value of a token, booleans are mapped to 0 and 1
+pub(crate) fn metric_value(token: &PublishToken) -> Option<f64> {
+    match token.value {
+        Value::Number(value) => Some(value),
+        Value::Integer(value) => Some(value as f64),
+        Value::Boolean(value) => Some(if value { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
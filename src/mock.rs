@@ -0,0 +1,198 @@
+//! Scripted device to test code using sessions without a real Tesira
+//!
+//! [MockTesira] is a stream sending the welcome banner, echoing received command lines
+//! and answering them with scripted lines. Handles are cheap to clone and share the same
+//! device, so one can be given to a session while another queues publish tokens.
+//!
+//! ```
+//! use tesira_text_protocol::{Command, TesiraSession, mock::MockTesira, proto::OkResponse};
+//!
+//! let device = MockTesira::new().on("Level3 set mute 1 true", "+OK");
+//! let mut session = TesiraSession::new_from_stream(device.clone(), device.clone()).unwrap();
+//!
+//! let response = session.send_command(Command::new_set("Level3", "mute", [1], true));
+//! assert_eq!(response.unwrap(), OkResponse::Ok);
+//! assert_eq!(device.received(), ["Level3 set mute 1 true"]);
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read, Write},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::proto::{IntoTTP, Value};
+
+/// Answer of commands without a scripted answer
+const UNKNOWN_COMMAND_ANSWER: &str = "-ERR address not found";
+
+/// Scripted Tesira device implementing [Read] and [Write]
+///
+/// Reading returns end of stream once every queued line was read.
+#[derive(Debug, Clone)]
+pub struct MockTesira {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    output: VecDeque<u8>,
+    line: Vec<u8>,
+    answers: HashMap<String, String>,
+    received: Vec<String>,
+}
+
+impl MockTesira {
+    /// Create a device which already sent its welcome banner
+    pub fn new() -> Self {
+        let mock = Self {
+            state: Arc::default(),
+        };
+        mock.push_line("Welcome to the Tesira Text Protocol Server...\n");
+        mock
+    }
+
+    /// Answer a command line with a response, such as `+OK "value":-10.000000`
+    ///
+    /// The answer is sent every time the line is received and may span several lines.
+    /// Commands without answer get `-ERR address not found`.
+    pub fn on(self, command: impl Into<String>, answer: impl Into<String>) -> Self {
+        self.state().answers.insert(command.into(), answer.into());
+        self
+    }
+
+    /// Queue an unsolicited publish token
+    pub fn publish(&self, label: impl Into<String>, value: impl IntoTTP) {
+        self.push_line(format!(
+            "! \"publishToken\":{} \"value\":{}",
+            Value::String(label.into()).into_ttp(),
+            value.into_ttp()
+        ));
+    }
+
+    /// Queue an arbitrary line, such as a malformed response
+    pub fn push_line(&self, line: impl AsRef<str>) {
+        let mut state = self.state();
+        state.output.extend(line.as_ref().as_bytes());
+        state.output.push_back(b'\n');
+    }
+
+    /// Command lines received so far, in order
+    pub fn received(&self) -> Vec<String> {
+        self.state().received.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // A panicking test may poison the lock, its state is still usable
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for MockTesira {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for MockTesira {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.state().output.read(buf)
+    }
+}
+
+impl Write for MockTesira {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state();
+        for byte in buf {
+            if *byte != b'\n' {
+                state.line.push(*byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&state.line).trim().to_owned();
+            state.line.clear();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Device echoes command before its answer
+            let answer = state
+                .answers
+                .get(&line)
+                .cloned()
+                .unwrap_or_else(|| UNKNOWN_COMMAND_ANSWER.to_owned());
+            for output in [line.as_str(), answer.as_str()] {
+                state.output.extend(output.as_bytes());
+                state.output.push_back(b'\n');
+            }
+            state.received.push(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::MockTesira;
+    use crate::{
+        Error, TesiraSession,
+        proto::{Command, ErrResponse, OkResponse, PublishToken, Value},
+    };
+
+    #[test]
+    fn should_answer_scripted_commands() {
+        let device = MockTesira::new()
+            .on("Level3 get level 1", "+OK \"value\":-10.000000")
+            .on("Level3 set mute 1 true", "+OK");
+        let mut session = TesiraSession::new_from_stream(device.clone(), device.clone()).unwrap();
+
+        assert_eq!(
+            session.get_f64(Command::new_get("Level3", "level", [1])),
+            Ok(-10.0)
+        );
+        assert_eq!(
+            session.send_command(Command::new_set("Level3", "mute", [1], true)),
+            Ok(OkResponse::Ok)
+        );
+        assert_eq!(
+            session.send_command(Command::new_get("Level4", "level", [1])),
+            Err(Error::CommandFailed {
+                command: "Level4 get level 1".to_owned(),
+                response: ErrResponse::new("address not found"),
+            })
+        );
+        assert_eq!(
+            device.received(),
+            [
+                "Level3 get level 1",
+                "Level3 set mute 1 true",
+                "Level4 get level 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_queue_publish_tokens() {
+        let device = MockTesira::new().on("Level3 subscribe level 1 MyLevel", "+OK");
+        let mut session = TesiraSession::new_from_stream(device.clone(), device.clone()).unwrap();
+
+        let receiver = session
+            .subscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+            .unwrap();
+        device.publish("MyLevel", -8.5);
+        session.dispatch_next_token().unwrap();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            PublishToken {
+                label: "MyLevel".to_owned(),
+                value: Value::Number(-8.5)
+            }
+        );
+        assert!(matches!(session.recv_token(), Err(Error::UnexpectedEnd)));
+    }
+}
@@ -1,17 +1,100 @@
 //! Implementation of protcol commands and basic blocs
 
+#[cfg(test)]
+mod arbitrary;
 pub mod commands;
+pub mod decoder;
+pub mod fault;
+pub mod network;
 pub mod parser;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod serialization;
 
-use chrono::{Datelike, naive::NaiveDateTime};
-use parser::parse_response;
-use std::{collections::HashMap, fmt::Display, time::Duration};
+use chrono::{NaiveDate, naive::NaiveDateTime};
+use parser::{parse_response, parse_value};
+use std::{fmt::Display, io, ops::Deref, time::Duration};
 use thiserror::Error;
 
 use crate::builder::CommandBuilder;
 
 /// Name of block a command can operate on
-pub type InstanceTag = String;
+///
+/// Tags containing whitespace are quoted when sent to device,
+/// use [InstanceTag::new] to check a tag coming from user input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstanceTag(String);
+
+impl InstanceTag {
+    /// Create an instance tag, checking it can be sent to device
+    ///
+    /// Empty tags and tags containing quotes, backslashes or control characters are rejected.
+    pub fn new(tag: impl Into<String>) -> Result<Self, InvalidInstanceTagError> {
+        let tag = tag.into();
+        if tag.is_empty()
+            || tag
+                .chars()
+                .any(|it| it == '"' || it == '\\' || it.is_control())
+        {
+            return Err(InvalidInstanceTagError(tag));
+        }
+        Ok(Self(tag))
+    }
+
+    /// Get the tag as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the tag must be quoted so that device reads it as a single token
+    fn needs_quoting(&self) -> bool {
+        self.0.is_empty()
+            || self
+                .0
+                .chars()
+                .any(|it| it.is_whitespace() || it == '"' || it == '\\' || it.is_control())
+    }
+}
+
+impl From<&str> for InstanceTag {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl From<String> for InstanceTag {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl Deref for InstanceTag {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for InstanceTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl IntoTTP for InstanceTag {
+    fn into_ttp(self) -> String {
+        if self.needs_quoting() {
+            Value::String(self.0).to_string()
+        } else {
+            self.0
+        }
+    }
+}
+
+/// Instance tag can't be sent to device, see [InstanceTag::new]
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Invalid instance tag: {0:?}")]
+pub struct InvalidInstanceTagError(pub String);
 
 /// Value of an index
 pub type IndexValue = u64;
@@ -47,7 +130,7 @@ impl<'a> Command<'a> {
 
     /// Create a new "get" command
     pub fn new_get(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
     ) -> Self {
@@ -62,7 +145,7 @@ impl<'a> Command<'a> {
 
     /// Create a new "set" command
     pub fn new_set(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
         value: impl IntoTTP,
@@ -76,9 +159,25 @@ impl<'a> Command<'a> {
         }
     }
 
+    /// Create a new "set" command with several values, such as a list of gains
+    pub fn new_set_many<T: IntoTTP>(
+        instance_tag: impl Into<InstanceTag>,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self {
+        Command {
+            instance_tag: instance_tag.into(),
+            command: commands::COMMAND_SET,
+            attribute,
+            indexes: indexes.into(),
+            values: values.into_iter().map(IntoTTP::into_ttp).collect(),
+        }
+    }
+
     /// Create a new "increment" command
     pub fn new_increment(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
         amount: impl IntoTTP,
@@ -94,7 +193,7 @@ impl<'a> Command<'a> {
 
     /// Create a new "decrement" command
     pub fn new_decrement(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
         amount: impl IntoTTP,
@@ -108,9 +207,24 @@ impl<'a> Command<'a> {
         }
     }
 
+    /// Create a new "toggle" command, such as for mute or bypass attributes
+    pub fn new_toggle(
+        instance_tag: impl Into<InstanceTag>,
+        attribute: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+    ) -> Self {
+        Command {
+            instance_tag: instance_tag.into(),
+            command: commands::COMMAND_TOGGLE,
+            attribute,
+            indexes: indexes.into(),
+            values: Vec::new(),
+        }
+    }
+
     /// Create a new "subscribe" command
     pub fn new_subscribe(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
         identifier: impl Into<String>,
@@ -126,7 +240,7 @@ impl<'a> Command<'a> {
 
     /// Create a new "subscribe" command with a minimum rate
     pub fn new_subscribe_with_rate(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
         identifier: impl Into<String>,
@@ -137,13 +251,13 @@ impl<'a> Command<'a> {
             command: commands::COMMAND_SUBSCRIBE,
             attribute,
             indexes: indexes.into(),
-            values: vec![identifier.into().into_ttp(), rate.as_millis().into_ttp()],
+            values: vec![identifier.into().into_ttp(), rate.into_ttp()],
         }
     }
 
     /// Create a new "unsubscribe" command
     pub fn new_unsubscribe(
-        instance_tag: impl Into<String>,
+        instance_tag: impl Into<InstanceTag>,
         attribute: &'a str,
         indexes: impl Into<Vec<IndexValue>>,
         identifier: impl Into<String>,
@@ -156,30 +270,160 @@ impl<'a> Command<'a> {
             values: vec![identifier.into().into_ttp()],
         }
     }
+
+    /// Create a new "dial" command on a telephony block
+    ///
+    /// Telephony commands have no attribute, indexes are usually the line
+    /// and the call appearance.
+    pub fn new_dial(
+        instance_tag: impl Into<InstanceTag>,
+        indexes: impl Into<Vec<IndexValue>>,
+        number: &str,
+    ) -> Self {
+        Command {
+            instance_tag: instance_tag.into(),
+            command: commands::COMMAND_DIAL,
+            attribute: "",
+            indexes: indexes.into(),
+            values: vec![Value::String(number.to_owned()).to_string()],
+        }
+    }
+
+    /// Create a new "speedDial" command on a telephony block
+    pub fn new_speed_dial(
+        instance_tag: impl Into<InstanceTag>,
+        indexes: impl Into<Vec<IndexValue>>,
+        entry: IndexValue,
+    ) -> Self {
+        Command {
+            instance_tag: instance_tag.into(),
+            command: commands::COMMAND_SPEED_DIAL,
+            attribute: "",
+            indexes: indexes.into(),
+            values: vec![entry.into_ttp()],
+        }
+    }
+
+    /// Create a new "dtmf" command on a telephony block, sending digits on a line
+    pub fn new_dtmf(
+        instance_tag: impl Into<InstanceTag>,
+        indexes: impl Into<Vec<IndexValue>>,
+        digits: &str,
+    ) -> Self {
+        Command {
+            instance_tag: instance_tag.into(),
+            command: commands::COMMAND_DTMF,
+            attribute: "",
+            indexes: indexes.into(),
+            values: vec![Value::String(digits.to_owned()).to_string()],
+        }
+    }
+
+    /// Create a new telephony command without value such as [commands::COMMAND_ANSWER],
+    /// [commands::COMMAND_END] or [commands::COMMAND_HOLD]
+    pub fn new_call_action(
+        instance_tag: impl Into<InstanceTag>,
+        action: &'a str,
+        indexes: impl Into<Vec<IndexValue>>,
+    ) -> Self {
+        Command {
+            instance_tag: instance_tag.into(),
+            command: action,
+            attribute: "",
+            indexes: indexes.into(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Create a new "recallPreset" command on device, presets are numbered from 1001
+    pub fn new_recall_preset(preset_id: IndexValue) -> Self {
+        Command {
+            instance_tag: InstanceTag::from("DEVICE"),
+            command: commands::COMMAND_RECALL_PRESET,
+            attribute: "",
+            indexes: Vec::new(),
+            values: vec![preset_id.into_ttp()],
+        }
+    }
+
+    /// Create a new "recallPresetByName" command on device
+    pub fn new_recall_preset_by_name(name: &str) -> Self {
+        Command {
+            instance_tag: InstanceTag::from("DEVICE"),
+            command: commands::COMMAND_RECALL_PRESET_BY_NAME,
+            attribute: "",
+            indexes: Vec::new(),
+            values: vec![Value::String(name.to_owned()).to_string()],
+        }
+    }
+
+    /// Create a new "reboot" command on device
+    pub fn new_reboot() -> Self {
+        Command {
+            instance_tag: InstanceTag::from("DEVICE"),
+            command: commands::COMMAND_REBOOT,
+            attribute: "",
+            indexes: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Append an already formatted token at the end of command values
+    ///
+    /// This is an escape hatch for device-specific extensions the typed API doesn't model,
+    /// the token is sent verbatim and the caller is responsible for its correctness.
+    pub fn append_raw_value(&mut self, token: &str) {
+        self.values.push(token.to_owned());
+    }
+
+    /// Write command to a writer as a line, without building an intermediate string
+    ///
+    /// Output is the same as [IntoTTP::into_ttp] followed by a newline.
+    /// Tokens are written one by one, prefer a buffered writer.
+    pub fn write_ttp(&self, w: &mut impl io::Write) -> io::Result<()> {
+        writeln!(w, "{self}")
+    }
+
+    /// Append a value at the end of command values if it is `Some`
+    ///
+    /// `None` adds no value at all, unlike [IntoTTP] for `Option` which sends an empty value.
+    pub fn append_optional_value(&mut self, value: Option<impl IntoTTP>) {
+        if let Some(value) = value {
+            self.values.push(value.into_ttp());
+        }
+    }
 }
 
 impl<'a> IntoTTP for Command<'a> {
     fn into_ttp(self) -> String {
-        let mut cmd_ttp = format!("{} {} {}", self.instance_tag, self.command, self.attribute); // [instance tag] [command str] [attribute str]
+        self.to_string()
+    }
+}
+
+/// Formats command as Tesira Text Protocol, same as [IntoTTP::into_ttp] without consuming it
+impl Display for Command<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // [instance tag] [command str]
+        if self.instance_tag.needs_quoting() {
+            write!(f, "{}", Value::String(self.instance_tag.to_string()))?;
+        } else {
+            f.write_str(&self.instance_tag)?;
+        }
+        write!(f, " {}", self.command)?;
+
+        if !self.attribute.is_empty() {
+            write!(f, " {}", self.attribute)?; // [attribute str], telephony commands have none
+        }
 
-        if !self.indexes.is_empty() {
-            cmd_ttp.push(' ');
-            cmd_ttp.push_str(
-                self.indexes
-                    .into_iter()
-                    .map(|it| it.to_string())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-                    .as_str(),
-            ); // [indexes...]
+        for index in &self.indexes {
+            write!(f, " {index}")?; // [indexes...]
         }
 
-        if !self.values.is_empty() {
-            cmd_ttp.push(' ');
-            cmd_ttp.push_str(self.values.join(" ").as_str()); // [values...]
+        for value in &self.values {
+            write!(f, " {value}")?; // [values...]
         }
 
-        cmd_ttp
+        Ok(())
     }
 }
 
@@ -189,6 +433,12 @@ impl IntoTTP for String {
     }
 }
 
+impl IntoTTP for &str {
+    fn into_ttp(self) -> String {
+        self.to_owned()
+    }
+}
+
 impl IntoTTP for bool {
     fn into_ttp(self) -> String {
         match self {
@@ -204,6 +454,24 @@ impl IntoTTP for i32 {
     }
 }
 
+impl IntoTTP for i64 {
+    fn into_ttp(self) -> String {
+        self.to_string()
+    }
+}
+
+impl IntoTTP for u32 {
+    fn into_ttp(self) -> String {
+        self.to_string()
+    }
+}
+
+impl IntoTTP for usize {
+    fn into_ttp(self) -> String {
+        self.to_string()
+    }
+}
+
 impl IntoTTP for u128 {
     fn into_ttp(self) -> String {
         self.to_string()
@@ -222,17 +490,335 @@ impl IntoTTP for f64 {
     }
 }
 
+impl IntoTTP for f32 {
+    fn into_ttp(self) -> String {
+        self.to_string()
+    }
+}
+
+/// `None` is serialized as an empty value, which still counts as a value on the wire
+///
+/// Use [Command::append_optional_value] to omit the value instead.
+impl<T: IntoTTP> IntoTTP for Option<T> {
+    fn into_ttp(self) -> String {
+        self.map(IntoTTP::into_ttp).unwrap_or_default()
+    }
+}
+
+/// Elements are separated by spaces
+impl<T: IntoTTP> IntoTTP for Vec<T> {
+    fn into_ttp(self) -> String {
+        self.into_iter()
+            .map(IntoTTP::into_ttp)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Elements are separated by spaces
+impl<T: IntoTTP + Clone> IntoTTP for &[T] {
+    fn into_ttp(self) -> String {
+        self.to_vec().into_ttp()
+    }
+}
+
+/// Serialized as a count of milliseconds, sub-millisecond precision is truncated
+impl IntoTTP for Duration {
+    fn into_ttp(self) -> String {
+        self.as_millis().into_ttp()
+    }
+}
+
 impl IntoTTP for NaiveDateTime {
     fn into_ttp(self) -> String {
-        format!(
-            "\"{}:{}:{}\"",
-            self.format("%H:%M:%S"),
-            self.month(),
-            self.format("%d:%Y")
-        )
+        FormattedDate::from(self).into_ttp()
+    }
+}
+
+/// Order of fields when sending a date to device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateFormat {
+    /// `H:M:S:month:day:Year`, used when sending a [NaiveDateTime]
+    #[default]
+    TimeMonthDayYear,
+    /// `H:M:S:day:month:Year`
+    TimeDayMonthYear,
+    /// A custom [chrono format](chrono::format::strftime), without surrounding quotes
+    Custom(&'static str),
+}
+
+impl DateFormat {
+    fn pattern(self) -> &'static str {
+        // Month and day are zero-padded like time fields
+        match self {
+            DateFormat::TimeMonthDayYear => "%H:%M:%S:%m:%d:%Y",
+            DateFormat::TimeDayMonthYear => "%H:%M:%S:%d:%m:%Y",
+            DateFormat::Custom(pattern) => pattern,
+        }
+    }
+}
+
+/// A date sent to device with a chosen [DateFormat]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormattedDate {
+    /// Date to send
+    pub date: NaiveDateTime,
+    /// Order of date fields expected by device
+    pub format: DateFormat,
+}
+
+impl FormattedDate {
+    /// Send a date with a given format
+    pub fn new(date: NaiveDateTime, format: DateFormat) -> Self {
+        Self { date, format }
     }
 }
 
+impl From<NaiveDateTime> for FormattedDate {
+    fn from(date: NaiveDateTime) -> Self {
+        Self::new(date, DateFormat::default())
+    }
+}
+
+impl IntoTTP for FormattedDate {
+    fn into_ttp(self) -> String {
+        Value::String(self.date.format(self.format.pattern()).to_string()).into_ttp()
+    }
+}
+
+impl IntoTTP for Value {
+    fn into_ttp(self) -> String {
+        self.to_string()
+    }
+}
+
+/// Formats value as Tesira Text Protocol
+///
+/// Numbers are written with six decimals like devices do, or in exponent notation when
+/// six decimals would lose precision. NaN and infinite numbers have no representation
+/// in the protocol, they are written as `NaN`, `inf` and `-inf` which devices reject.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(v) => {
+                // Devices use six fixed decimals, values they can't hold are kept exact
+                let fixed = format!("{v:.6}");
+                if !v.is_finite() || fixed.parse::<f64>() == Ok(*v) {
+                    write!(f, "{fixed}")
+                } else {
+                    write!(f, "{v:e}")
+                }
+            }
+            Value::Integer(v) => write!(f, "{v}"),
+            Value::Boolean(v) => write!(f, "{v}"),
+            Value::String(v) => write!(
+                f,
+                "\"{}\"",
+                v.replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+            ),
+            Value::Constant(v) => write!(f, "{v}"),
+            Value::Map(v) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "\"{key}\":{value}")?;
+                }
+                write!(f, "}}")
+            }
+            Value::Array(v) => {
+                write!(f, "[")?;
+                for (i, value) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Conversion trait from Tesira Text Protocol values
+///
+/// Implemented for tuples to destructure fixed-layout arrays positionally
+pub trait FromTTP: Sized {
+    /// Convert a parsed Tesira Text Protocol value to this type
+    fn from_ttp_value(value: Value) -> Result<Self, Error>;
+
+    /// Same as [FromTTP::from_ttp_value] from a value string, inverse of [IntoTTP::into_ttp]
+    fn from_ttp(input: &str) -> Result<Self, Error> {
+        let value = parse_value(input.trim()).map_err(into_error)?.1;
+        Self::from_ttp_value(value)
+    }
+}
+
+impl FromTTP for Value {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl FromTTP for f64 {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Number(v) => Ok(v),
+            Value::Integer(v) => Ok(v as f64),
+            v => Err(Error::WrongValueType {
+                expected: "number",
+                got: v.type_name(),
+            }),
+        }
+    }
+}
+
+impl FromTTP for i64 {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Integer(v) => Ok(v),
+            v => Err(Error::WrongValueType {
+                expected: "integer",
+                got: v.type_name(),
+            }),
+        }
+    }
+}
+
+impl FromTTP for bool {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Boolean(v) => Ok(v),
+            v => Err(Error::WrongValueType {
+                expected: "boolean",
+                got: v.type_name(),
+            }),
+        }
+    }
+}
+
+impl FromTTP for String {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(v) | Value::Constant(v) => Ok(v),
+            v => Err(Error::WrongValueType {
+                expected: "string",
+                got: v.type_name(),
+            }),
+        }
+    }
+}
+
+impl FromTTP for NaiveDateTime {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        // Sent as "H:M:S:month:day:Year"
+        let date = String::from_ttp_value(value)?;
+        let fields = date
+            .split(':')
+            .map(|it| it.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>();
+        match fields.as_deref() {
+            Ok(&[hour, minute, second, month, day, year]) => {
+                NaiveDate::from_ymd_opt(year as i32, month, day)
+                    .and_then(|it| it.and_hms_opt(hour, minute, second))
+            }
+            _ => None,
+        }
+        .ok_or_else(|| Error::InvalidValue(format!("invalid date {date}")))
+    }
+}
+
+impl FromTTP for ValueMap {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Map(v) => Ok(v),
+            v => Err(Error::WrongValueType {
+                expected: "map",
+                got: v.type_name(),
+            }),
+        }
+    }
+}
+
+impl<T: FromTTP> FromTTP for Vec<T> {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Array(v) => v.into_iter().map(T::from_ttp_value).collect(),
+            v => Err(Error::WrongValueType {
+                expected: "array",
+                got: v.type_name(),
+            }),
+        }
+    }
+}
+
+/// Remove a key from a map value and convert it
+pub(crate) fn take_key<T: FromTTP>(map: &mut ValueMap, key: &str) -> Result<T, Error> {
+    #[cfg(feature = "indexmap")]
+    let value = map.shift_remove(key);
+    #[cfg(not(feature = "indexmap"))]
+    let value = map.remove(key);
+    T::from_ttp_value(value.ok_or_else(|| Error::MissingKey(key.to_owned()))?)
+}
+
+macro_rules! impl_try_from_value {
+    ($($target:ty),+) => {
+        $(
+            impl TryFrom<Value> for $target {
+                type Error = Error;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    Self::from_ttp_value(value)
+                }
+            }
+        )+
+    };
+}
+
+impl_try_from_value!(f64, i64, bool, String, NaiveDateTime);
+
+macro_rules! impl_from_ttp_for_tuple {
+    ($len:expr => $($name:ident),+) => {
+        impl<$($name: FromTTP),+> FromTTP for ($($name,)+) {
+            fn from_ttp_value(value: Value) -> Result<Self, Error> {
+                let values = match value {
+                    Value::Array(values) => values,
+                    v => {
+                        return Err(Error::WrongValueType {
+                            expected: "array",
+                            got: v.type_name(),
+                        })
+                    }
+                };
+                if values.len() != $len {
+                    return Err(Error::WrongArrayLength {
+                        expected: $len,
+                        got: values.len(),
+                    });
+                }
+                let mut values = values.into_iter();
+                Ok(($($name::from_ttp_value(values.next().unwrap())?,)+))
+            }
+        }
+    };
+}
+
+impl_from_ttp_for_tuple!(1 => A);
+impl_from_ttp_for_tuple!(2 => A, B);
+impl_from_ttp_for_tuple!(3 => A, B, C);
+impl_from_ttp_for_tuple!(4 => A, B, C, D);
+impl_from_ttp_for_tuple!(5 => A, B, C, D, E);
+impl_from_ttp_for_tuple!(6 => A, B, C, D, E, F);
+impl_from_ttp_for_tuple!(7 => A, B, C, D, E, F, G);
+impl_from_ttp_for_tuple!(8 => A, B, C, D, E, F, G, H);
+impl_from_ttp_for_tuple!(9 => A, B, C, D, E, F, G, H, I);
+impl_from_ttp_for_tuple!(10 => A, B, C, D, E, F, G, H, I, J);
+impl_from_ttp_for_tuple!(11 => A, B, C, D, E, F, G, H, I, J, K);
+impl_from_ttp_for_tuple!(12 => A, B, C, D, E, F, G, H, I, J, K, L);
+
 /// A response from device to a command
 #[derive(Debug, Clone, PartialEq)]
 pub enum Response {
@@ -249,6 +835,23 @@ pub enum Response {
 pub struct ErrResponse {
     /// Device message decribing the error
     pub message: String,
+    /// Map ending first line of message, such as `{"deviceId":0 "classCode":0 "instanceNum":0}`
+    ///
+    /// Boxed to keep error types small.
+    pub body: Option<Box<Value>>,
+}
+
+impl ErrResponse {
+    /// Create an error response from device message, parsing its trailing map if any
+    pub fn new(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let first_line = message.lines().next().unwrap_or_default();
+        let body = first_line
+            .match_indices('{')
+            .find_map(|(index, _)| parse_value(&first_line[index..]).ok())
+            .map(|(_, value)| Box::new(value));
+        Self { message, body }
+    }
 }
 
 impl Display for ErrResponse {
@@ -277,58 +880,214 @@ pub struct PublishToken {
     pub value: Value,
 }
 
+/// Serialized as the line sent by device, without line terminator
+impl IntoTTP for Response {
+    fn into_ttp(self) -> String {
+        match self {
+            Response::Ok(response) => response.into_ttp(),
+            Response::Err(response) => response.into_ttp(),
+            Response::PublishToken(token) => token.into_ttp(),
+        }
+    }
+}
+
+impl IntoTTP for OkResponse {
+    fn into_ttp(self) -> String {
+        match self {
+            OkResponse::Ok => "+OK".to_owned(),
+            OkResponse::WithValue(value) => format!("+OK \"value\":{value}"),
+            OkResponse::WithList(list) => format!("+OK \"list\":{}", Value::Array(list)),
+        }
+    }
+}
+
+impl IntoTTP for ErrResponse {
+    fn into_ttp(self) -> String {
+        if self.message.is_empty() {
+            return "-ERR".to_owned();
+        }
+        format!("-ERR {}", self.message)
+    }
+}
+
+impl IntoTTP for PublishToken {
+    fn into_ttp(self) -> String {
+        format!(
+            "! \"publishToken\":{} \"value\":{}",
+            Value::String(self.label),
+            self.value
+        )
+    }
+}
+
+/// Key-value map of [Value::Map]
+///
+/// With the `indexmap` feature, keys are kept in the order sent by device
+#[cfg(feature = "indexmap")]
+pub type ValueMap = indexmap::IndexMap<String, Value>;
+
+/// Key-value map of [Value::Map]
+///
+/// With the `indexmap` feature, keys are kept in the order sent by device
+#[cfg(not(feature = "indexmap"))]
+pub type ValueMap = std::collections::HashMap<String, Value>;
+
 /// A structured value from Tesira devices
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     /// A floating point number
     Number(f64),
+    /// An integer number, sent without fractional part nor exponent
+    Integer(i64),
     /// A boolean value
     Boolean(bool),
     /// Any string value
     String(String),
     /// A JSON-like object key-value map
-    Map(HashMap<String, Value>),
+    Map(ValueMap),
     /// A sequence of heterogenous values
     Array(Vec<Value>),
     /// A constant value described by a string such as "DHCP", "LINK_1_GB", etc.
     Constant(String),
 }
 
+impl Value {
+    /// Merge a partial update into this value
+    ///
+    /// Maps are merged recursively key by key, any other value replaces the current one.
+    pub fn deep_merge(&mut self, update: Value) {
+        match (self, update) {
+            (Value::Map(current), Value::Map(update)) => {
+                for (key, value) in update {
+                    match current.get_mut(&key) {
+                        Some(current_value) => current_value.deep_merge(value),
+                        None => {
+                            current.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (current, update) => *current = update,
+        }
+    }
+
+    /// Get a nested value from a path of dotted map keys and bracketed array indexes
+    ///
+    /// Such as `networkInterfaceStatusWithName[0].networkInterfaceStatus.ip`.
+    /// Returns `None` if a key is missing, an index is out of range or a value along
+    /// the path is not of the expected type.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        let mut current = self;
+        for segment in path.split('.') {
+            let (key, mut indexes) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+            if !key.is_empty() {
+                current = match current {
+                    Value::Map(values) => values.get(key)?,
+                    _ => return None,
+                };
+            }
+            while !indexes.is_empty() {
+                let (index, rest) = indexes.strip_prefix('[')?.split_once(']')?;
+                current = match current {
+                    Value::Array(values) => values.get(index.parse::<usize>().ok()?)?,
+                    _ => return None,
+                };
+                indexes = rest;
+            }
+        }
+        Some(current)
+    }
+
+    /// Human readable name of the value type
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Map(_) => "map",
+            Value::Array(_) => "array",
+            Value::Constant(_) => "constant",
+        }
+    }
+}
+
 impl Response {
     /// Parse ttp string into response
     pub fn parse_ttp(source: &str) -> Result<Self, Error> {
-        parse_response(source).map(|it| it.1).map_err(|e| match e {
-            nom::Err::Error(e) | nom::Err::Failure(e) => Error::ParseError(e),
-            nom::Err::Incomplete(_e) => Error::UnexpectedEnd,
-        })
+        parse_response(source).map(|it| it.1).map_err(into_error)
+    }
+}
+
+/// Maximum number of characters of input kept in [Error::ParseError]
+const PARSE_ERROR_SNIPPET_LEN: usize = 64;
+
+fn into_error(error: nom::Err<nom::error::Error<&str>>) -> Error {
+    match error {
+        nom::Err::Error(e) | nom::Err::Failure(e) => Error::ParseError {
+            input: e.input.chars().take(PARSE_ERROR_SNIPPET_LEN).collect(),
+            kind: e.code,
+        },
+        nom::Err::Incomplete(_e) => Error::UnexpectedEnd,
     }
 }
 
 /// A parsing error of response
-#[derive(Debug, Error)]
-pub enum Error<'a> {
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
     /// Error while parsing response
-    #[error("Response parse error: {0}")]
-    ParseError(nom::error::Error<&'a str>),
+    #[error("Response parse error: {kind:?} at '{input}'")]
+    ParseError {
+        /// Beginning of input remaining where parsing failed
+        input: String,
+        /// Kind of parser that failed
+        kind: nom::error::ErrorKind,
+    },
     /// More data is required to complete response parsing
     #[error("Unexpected end of input")]
     UnexpectedEnd,
+    /// Value is not of the expected type
+    #[error("Wrong value type: expected {expected}, got {got}")]
+    WrongValueType {
+        /// Expected value type
+        expected: &'static str,
+        /// Actual value type
+        got: &'static str,
+    },
+    /// Array does not have the expected number of elements
+    #[error("Wrong array length: expected {expected}, got {got}")]
+    WrongArrayLength {
+        /// Expected number of elements
+        expected: usize,
+        /// Actual number of elements
+        got: usize,
+    },
+    /// Map does not contain an expected key
+    #[error("Missing key '{0}'")]
+    MissingKey(String),
+    /// Value is of the expected type but not supported
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
 }
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
-
     use crate::proto::ErrResponse;
     use crate::proto::OkResponse;
     use crate::proto::PublishToken;
     use crate::proto::Response;
     use crate::proto::Value;
+    use crate::proto::ValueMap;
     use chrono::NaiveDateTime;
     use pretty_assertions::assert_eq;
+    use std::time::Duration;
 
     use super::Command;
+    use super::Error;
+    use super::FromTTP;
     use super::IntoTTP;
+    use super::commands;
+    use super::{DateFormat, FormattedDate, InstanceTag, InvalidInstanceTagError};
 
     #[test]
     fn should_serialize_date() {
@@ -336,8 +1095,67 @@ mod test {
             NaiveDateTime::parse_from_str("2025-06-01T12:56:43.000Z", "%+")
                 .unwrap()
                 .into_ttp(),
-            "\"12:56:43:6:01:2025\""
-        )
+            "\"12:56:43:06:01:2025\""
+        );
+        assert_eq!(
+            NaiveDateTime::parse_from_str("2024-12-25T08:05:09.000Z", "%+")
+                .unwrap()
+                .into_ttp(),
+            "\"08:05:09:12:25:2024\""
+        );
+    }
+
+    #[test]
+    fn should_serialize_date_with_format() {
+        let date = NaiveDateTime::parse_from_str("2025-06-01T12:56:43.000Z", "%+").unwrap();
+        assert_eq!(
+            FormattedDate::new(date, DateFormat::TimeMonthDayYear).into_ttp(),
+            date.into_ttp()
+        );
+        assert_eq!(
+            FormattedDate::new(date, DateFormat::TimeDayMonthYear).into_ttp(),
+            "\"12:56:43:01:06:2025\""
+        );
+        assert_eq!(
+            FormattedDate::new(date, DateFormat::Custom("%Y-%m-%d %H:%M:%S")).into_ttp(),
+            "\"2025-06-01 12:56:43\""
+        );
+    }
+
+    #[test]
+    fn should_return_owned_parse_error() {
+        let error = {
+            let source = String::from("?unknown response");
+            Response::parse_ttp(&source).unwrap_err()
+        };
+        assert_eq!(
+            error,
+            Error::ParseError {
+                input: "?unknown response".to_owned(),
+                kind: nom::error::ErrorKind::Tag
+            }
+        );
+    }
+
+    #[test]
+    fn should_parse_from_ttp() {
+        assert!(bool::from_ttp("true").unwrap());
+        assert_eq!(f64::from_ttp("-10.500000").unwrap(), -10.5);
+        assert_eq!(f64::from_ttp("3").unwrap(), 3.0);
+        assert_eq!(
+            String::from_ttp("\"a \\\"quoted\\\" word\"").unwrap(),
+            "a \"quoted\" word"
+        );
+        assert_eq!(
+            NaiveDateTime::from_ttp("\"12:56:43:6:01:2025\"").unwrap(),
+            NaiveDateTime::parse_from_str("2025-06-01T12:56:43", "%Y-%m-%dT%H:%M:%S").unwrap()
+        );
+        assert!(bool::from_ttp("1").is_err());
+        assert!(f64::from_ttp("12 13").is_err());
+        assert!(matches!(
+            NaiveDateTime::from_ttp("\"12:56:43:13:01:2025\""),
+            Err(Error::InvalidValue(_))
+        ));
     }
 
     #[test]
@@ -356,6 +1174,104 @@ mod test {
         );
     }
 
+    #[test]
+    fn should_quote_instance_tag_with_space() {
+        assert_eq!(
+            Command::new_get("Level 3", "level", [2]).into_ttp(),
+            "\"Level 3\" get level 2"
+        );
+    }
+
+    #[test]
+    fn should_check_instance_tag() {
+        assert_eq!(InstanceTag::new("Level 3").unwrap().as_str(), "Level 3");
+        assert_eq!(
+            InstanceTag::new("Level\"3"),
+            Err(InvalidInstanceTagError("Level\"3".to_owned()))
+        );
+        assert!(InstanceTag::new("Level\n3").is_err());
+        assert!(InstanceTag::new("").is_err());
+    }
+
+    #[test]
+    fn should_display_command_as_ttp() {
+        for command in [
+            Command::new_get("SESSION", "aliases", []),
+            Command::new_get("Level 3", "level", [2]),
+            Command::new_set("Level3", "mute", [1, 2], true),
+            Command::new_call_action("Dialer1", commands::COMMAND_REDIAL, [1, 1]),
+        ] {
+            let displayed = command.to_string();
+            assert_eq!(displayed, command.into_ttp());
+        }
+    }
+
+    #[test]
+    fn should_serialize_toggle_command() {
+        assert_eq!(
+            Command::new_toggle("Level3", "mute", [1]).into_ttp(),
+            "Level3 toggle mute 1"
+        );
+
+        assert_eq!(
+            Command::new_toggle("PEQ1", "bypassAll", []).into_ttp(),
+            "PEQ1 toggle bypassAll"
+        );
+    }
+
+    #[test]
+    fn should_serialize_telephony_commands() {
+        assert_eq!(
+            Command::new_dial("VoIP1", [1, 1], "5551212").into_ttp(),
+            "VoIP1 dial 1 1 \"5551212\""
+        );
+        assert_eq!(
+            Command::new_dial("VoIP1", [1, 1], "555\"\nDEVICE reboot").into_ttp(),
+            "VoIP1 dial 1 1 \"555\\\"\\nDEVICE reboot\""
+        );
+        assert_eq!(
+            Command::new_speed_dial("Dialer1", [1, 2], 3).into_ttp(),
+            "Dialer1 speedDial 1 2 3"
+        );
+        assert_eq!(
+            Command::new_dtmf("VoIP1", [1], "5#").into_ttp(),
+            "VoIP1 dtmf 1 \"5#\""
+        );
+        assert_eq!(
+            Command::new_dtmf("VoIP1", [1], "5\nDEVICE reboot").into_ttp(),
+            "VoIP1 dtmf 1 \"5\\nDEVICE reboot\""
+        );
+        assert_eq!(
+            Command::new_call_action("VoIP1", commands::COMMAND_ANSWER, [1, 1]).into_ttp(),
+            "VoIP1 answer 1 1"
+        );
+        assert_eq!(
+            Command::new_call_action("TI1", commands::COMMAND_OFF_HOOK, []).into_ttp(),
+            "TI1 offHook"
+        );
+    }
+
+    #[test]
+    fn should_serialize_recall_preset_commands() {
+        assert_eq!(
+            Command::new_recall_preset(1001).into_ttp(),
+            "DEVICE recallPreset 1001"
+        );
+        assert_eq!(
+            Command::new_recall_preset_by_name("Night Mode").into_ttp(),
+            "DEVICE recallPresetByName \"Night Mode\""
+        );
+        assert_eq!(
+            Command::new_recall_preset_by_name("Night \"Mode\" \\ 2").into_ttp(),
+            "DEVICE recallPresetByName \"Night \\\"Mode\\\" \\\\ 2\""
+        );
+    }
+
+    #[test]
+    fn should_serialize_reboot_command() {
+        assert_eq!(Command::new_reboot().into_ttp(), "DEVICE reboot");
+    }
+
     #[test]
     fn should_serialize_set_command() {
         assert_eq!(
@@ -369,6 +1285,82 @@ mod test {
         );
     }
 
+    #[test]
+    fn should_serialize_primitive_values() {
+        assert_eq!("RAW value".into_ttp(), "RAW value");
+        assert_eq!(7u32.into_ttp(), "7");
+        assert_eq!((-7i64).into_ttp(), "-7");
+        assert_eq!(12usize.into_ttp(), "12");
+        assert_eq!((-3.5f32).into_ttp(), "-3.5");
+        assert_eq!(Duration::from_millis(250).into_ttp(), "250");
+        assert_eq!(Duration::from_micros(1999).into_ttp(), "1");
+        assert_eq!(
+            Command::new_set("Level3", "level", [1], -10.5f32).into_ttp(),
+            "Level3 set level 1 -10.5"
+        );
+    }
+
+    #[test]
+    fn should_serialize_optional_value() {
+        assert_eq!(Some(3).into_ttp(), "3");
+        assert_eq!(None::<i32>.into_ttp(), "");
+        assert_eq!(
+            Command::new_set("Level3", "label", [1], None::<String>).into_ttp(),
+            "Level3 set label 1 "
+        );
+
+        let mut command = Command::new_get("Level3", "level", [1]);
+        command.append_optional_value(None::<f64>);
+        assert_eq!(command.into_ttp(), "Level3 get level 1");
+
+        let mut command = Command::new_set("Level3", "level", [1], -10);
+        command.append_optional_value(Some(Duration::from_millis(500)));
+        assert_eq!(command.into_ttp(), "Level3 set level 1 -10 500");
+    }
+
+    #[test]
+    fn should_serialize_many_values() {
+        assert_eq!(vec![1.0, 2.0, 3.0].into_ttp(), "1 2 3");
+        assert_eq!([true, false].as_slice().into_ttp(), "true false");
+        assert_eq!(Vec::<i32>::new().into_ttp(), "");
+
+        let command = Command::new_set_many("Mixer1", "gains", [1], vec![-10.0, -3.5, 0.0]);
+        assert_eq!(command.values.len(), 3);
+        assert_eq!(command.into_ttp(), "Mixer1 set gains 1 -10 -3.5 0");
+    }
+
+    #[test]
+    fn should_write_same_bytes_as_into_ttp() {
+        let commands = [
+            Command::new_get("Level3", "level", [1]),
+            Command::new_set("Level 3", "label", [2], "Mic \"A\"".to_owned()),
+            Command::new_subscribe_with_rate(
+                "Meter1",
+                "levels",
+                [],
+                "MyMeter",
+                Duration::from_millis(100),
+            ),
+            Command::new_call_action("Dialer1", commands::COMMAND_ANSWER, [1, 1]),
+        ];
+        for command in commands {
+            let mut written = Vec::new();
+            command.write_ttp(&mut written).unwrap();
+            assert_eq!(written, format!("{}\n", command.into_ttp()).into_bytes());
+        }
+    }
+
+    #[test]
+    fn should_serialize_command_with_raw_value() {
+        let mut command = Command::new_set("level3", "mute", [3], true);
+        command.append_raw_value("{\"ramp\":250}");
+        assert_eq!(command.into_ttp(), "level3 set mute 3 true {\"ramp\":250}");
+
+        let mut command = Command::new_get("DEVICE", "version", []);
+        command.append_raw_value("extra");
+        assert_eq!(command.into_ttp(), "DEVICE get version extra");
+    }
+
     #[test]
     fn should_parse_simple_ok_response() {
         assert_eq!(
@@ -385,6 +1377,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn should_parse_response_with_extra_whitespace() {
+        assert_eq!(
+            Response::parse_ttp("+OK   \"value\":1").unwrap(),
+            Response::Ok(OkResponse::WithValue(Value::Integer(1)))
+        );
+        assert_eq!(
+            Response::parse_ttp("+OK   \n").unwrap(),
+            Response::Ok(OkResponse::Ok)
+        );
+        assert_eq!(
+            Response::parse_ttp("+OK \"value\":[ 1  2 ]  \n").unwrap(),
+            Response::Ok(OkResponse::WithValue(Value::Array(vec![
+                Value::Integer(1),
+                Value::Integer(2)
+            ])))
+        );
+        assert_eq!(
+            Response::parse_ttp("+OK \"value\":{ \"mute\":true }").unwrap(),
+            Response::Ok(OkResponse::WithValue(Value::Map(ValueMap::from_iter([(
+                "mute".to_owned(),
+                Value::Boolean(true)
+            )]))))
+        );
+        assert_eq!(
+            Response::parse_ttp("-ERR   address not found   \n").unwrap(),
+            Response::Err(ErrResponse::new("address not found"))
+        );
+        assert_eq!(
+            Response::parse_ttp("!  \"publishToken\":\"MyLevel\"   \"value\":-10  \n").unwrap(),
+            Response::PublishToken(PublishToken {
+                label: "MyLevel".to_owned(),
+                value: Value::Integer(-10)
+            })
+        );
+    }
+
     #[test]
     fn should_parse_ok_response_with_empty_string_value() {
         assert_eq!(
@@ -396,7 +1425,7 @@ mod test {
     #[test]
     fn should_parse_ok_response_with_array_value() {
         let expected_value = Value::Array(vec![
-            Value::Number(2.0),
+            Value::Integer(2),
             Value::String("TesiraForte05953601".to_owned()),
             Value::String("0.0.0.0".to_owned()),
             Value::Boolean(true),
@@ -410,10 +1439,95 @@ mod test {
         assert_eq!(Response::parse_ttp("+OK \"value\":[2 \"TesiraForte05953601\" \"0.0.0.0\" true true false false false false]").unwrap(), Response::Ok(OkResponse::WithValue(expected_value)));
     }
 
+    #[test]
+    fn should_convert_array_value_to_tuple() {
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp(
+            "+OK \"value\":[2 \"TesiraForte05953601\" \"0.0.0.0\" true true false false false false]",
+        )
+        .unwrap() else {
+            panic!("Expected a response with value");
+        };
+
+        assert_eq!(
+            <(f64, String, String, bool, bool, bool, bool, bool, bool)>::from_ttp_value(
+                value.clone()
+            )
+            .unwrap(),
+            (
+                2.0,
+                "TesiraForte05953601".to_owned(),
+                "0.0.0.0".to_owned(),
+                true,
+                true,
+                false,
+                false,
+                false,
+                false
+            )
+        );
+
+        assert!(matches!(
+            <(f64, String)>::from_ttp_value(value.clone()),
+            Err(Error::WrongArrayLength {
+                expected: 2,
+                got: 9
+            })
+        ));
+        assert!(matches!(
+            <(String, String, String, bool, bool, bool, bool, bool, bool)>::from_ttp_value(value),
+            Err(Error::WrongValueType {
+                expected: "string",
+                got: "integer"
+            })
+        ));
+    }
+
+    #[test]
+    fn should_deep_merge_values() {
+        let mut value = Value::Map(ValueMap::from([
+            (
+                "state".to_owned(),
+                Value::Map(ValueMap::from([
+                    ("line".to_owned(), Value::Number(1.0)),
+                    (
+                        "callState".to_owned(),
+                        Value::Constant("CONNECTED".to_owned()),
+                    ),
+                ])),
+            ),
+            ("callerId".to_owned(), Value::String("123".to_owned())),
+        ]));
+
+        value.deep_merge(Value::Map(ValueMap::from([(
+            "state".to_owned(),
+            Value::Map(ValueMap::from([(
+                "callState".to_owned(),
+                Value::Constant("IDLE".to_owned()),
+            )])),
+        )])));
+
+        assert_eq!(
+            value,
+            Value::Map(ValueMap::from([
+                (
+                    "state".to_owned(),
+                    Value::Map(ValueMap::from([
+                        ("line".to_owned(), Value::Number(1.0)),
+                        ("callState".to_owned(), Value::Constant("IDLE".to_owned())),
+                    ])),
+                ),
+                ("callerId".to_owned(), Value::String("123".to_owned())),
+            ]))
+        );
+
+        value.deep_merge(Value::Number(2.0));
+        assert_eq!(value, Value::Number(2.0));
+    }
+
     #[test]
     fn should_parse_ok_response_with_map_value() {
-        let expected_value = Value::Map(HashMap::from([
-            ("schemaVersion".to_owned(), Value::Number(2.0)),
+        let expected_value = Value::Map(ValueMap::from([
+            ("schemaVersion".to_owned(), Value::Integer(2)),
             (
                 "hostname".to_owned(),
                 Value::String("TesiraForte05953601".to_owned()),
@@ -445,8 +1559,8 @@ mod test {
 
     #[test]
     fn should_parse_ok_response_with_nested_value() {
-        let expected_value = Value::Map(HashMap::from([
-            ("schemaVersion".to_owned(), Value::Number(2.0)),
+        let expected_value = Value::Map(ValueMap::from([
+            ("schemaVersion".to_owned(), Value::Integer(2)),
             (
                 "hostname".to_owned(),
                 Value::String("TesiraForte05953601".to_owned()),
@@ -457,14 +1571,14 @@ mod test {
             ),
             (
                 "networkInterfaceStatusWithName".to_owned(),
-                Value::Array(vec![Value::Map(HashMap::from([
+                Value::Array(vec![Value::Map(ValueMap::from([
                     (
                         "interfaceId".to_owned(),
                         Value::String("control".to_owned()),
                     ),
                     (
                         "networkInterfaceStatus".to_owned(),
-                        Value::Map(HashMap::from([
+                        Value::Map(ValueMap::from([
                             (
                                 "macAddress".to_owned(),
                                 Value::String("78:45:01:3d:86:92".to_owned()),
@@ -497,7 +1611,7 @@ mod test {
             ),
             (
                 "dnsStatus".to_owned(),
-                Value::Map(HashMap::from([
+                Value::Map(ValueMap::from([
                     (
                         "primaryDNSServer".to_owned(),
                         Value::String("10.0.148.1".to_owned()),
@@ -571,6 +1685,175 @@ mod test {
         }));
     }
 
+    #[test]
+    fn should_reject_unknown_unsolicited_lines() {
+        // Publish tokens are the only documented unsolicited messages
+        assert!(Response::parse_ttp("! \"event\":\"presetRecalled\" \"preset\":1001").is_err());
+        assert!(Response::parse_ttp("! \"publishToken\":\"MyLevel4CH1\"").is_err());
+    }
+
+    #[test]
+    fn should_serialize_responses() {
+        let responses = [
+            (Response::Ok(OkResponse::Ok), "+OK"),
+            (
+                Response::Ok(OkResponse::WithValue(Value::Number(1.0))),
+                "+OK \"value\":1.000000",
+            ),
+            (
+                Response::Ok(OkResponse::WithList(vec![
+                    Value::String("Level1".to_owned()),
+                    Value::String("Mixer1".to_owned()),
+                ])),
+                "+OK \"list\":[\"Level1\" \"Mixer1\"]",
+            ),
+            (Response::Err(ErrResponse::new("")), "-ERR"),
+            (
+                Response::Err(ErrResponse::new(
+                    "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}",
+                )),
+                "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}",
+            ),
+            (
+                Response::PublishToken(PublishToken {
+                    label: "MyLevel".to_owned(),
+                    value: Value::Array(vec![Value::Number(-10.0), Value::Boolean(true)]),
+                }),
+                "! \"publishToken\":\"MyLevel\" \"value\":[-10.000000 true]",
+            ),
+        ];
+        for (response, line) in responses {
+            assert_eq!(response.clone().into_ttp(), line);
+            assert_eq!(Response::parse_ttp(line).unwrap(), response);
+        }
+    }
+
+    #[test]
+    fn should_round_trip_value_to_ttp() {
+        let source = include_str!("../tests/corpus/ok_value_network_status.ttp");
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp(source).unwrap()
+        else {
+            panic!("Network status sample is not a value");
+        };
+
+        let serialized = format!("+OK \"value\":{}", value.clone().into_ttp());
+        assert_eq!(
+            Response::parse_ttp(&serialized).unwrap(),
+            Response::Ok(OkResponse::WithValue(value))
+        );
+
+        assert_eq!(Value::Number(-10.0).into_ttp(), "-10.000000");
+        assert_eq!(
+            Value::Array(vec![
+                Value::String("control".to_owned()),
+                Value::Constant("DHCP".to_owned()),
+                Value::Boolean(false)
+            ])
+            .into_ttp(),
+            "[\"control\" DHCP false]"
+        );
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn should_keep_map_keys_in_device_order() {
+        let source = include_str!("../tests/corpus/ok_value_network_status.ttp").trim_end();
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp(source).unwrap()
+        else {
+            panic!("Network status sample is not a value");
+        };
+
+        assert_eq!(format!("+OK \"value\":{}", value.into_ttp()), source);
+    }
+
+    #[test]
+    fn should_display_value_as_ttp() {
+        assert_eq!(Value::Number(6.0).to_string(), "6.000000");
+        assert_eq!(Value::Number(-0.5).to_string(), "-0.500000");
+        assert_eq!(Value::Number(0.1).to_string(), "0.100000");
+        assert_eq!(Value::Number(1e-9).to_string(), "1e-9");
+        assert_eq!(Value::Number(20.0000001).to_string(), "2.00000001e1");
+        assert_eq!(
+            Response::parse_ttp("+OK \"value\":1e-9").unwrap(),
+            Response::Ok(OkResponse::WithValue(Value::Number(1e-9)))
+        );
+        assert_eq!(Value::Number(f64::NAN).to_string(), "NaN");
+        assert_eq!(Value::Number(f64::NEG_INFINITY).to_string(), "-inf");
+        assert_eq!(Value::Boolean(true).to_string(), "true");
+        assert_eq!(Value::String("Level1".to_owned()).to_string(), "\"Level1\"");
+        assert_eq!(Value::String(String::new()).to_string(), "\"\"");
+        assert_eq!(
+            Value::String("a \"quoted\" C:\\path".to_owned()).to_string(),
+            "\"a \\\"quoted\\\" C:\\\\path\""
+        );
+        assert_eq!(
+            Value::Constant("LINK_1_GB".to_owned()).to_string(),
+            "LINK_1_GB"
+        );
+        assert_eq!(Value::Map(ValueMap::new()).to_string(), "{}");
+        assert_eq!(Value::Array(Vec::new()).to_string(), "[]");
+        assert_eq!(
+            Value::Map(ValueMap::from([(
+                "levels".to_owned(),
+                Value::Array(vec![Value::Number(-10.0), Value::Boolean(false)])
+            )]))
+            .to_string(),
+            "{\"levels\":[-10.000000 false]}"
+        );
+    }
+
+    #[test]
+    fn should_try_convert_value_to_primitives() {
+        let level: f64 = Value::Number(-10.5).try_into().unwrap();
+        assert_eq!(level, -10.5);
+        let mute: bool = Value::Boolean(true).try_into().unwrap();
+        assert!(mute);
+        let label: String = Value::String("Level1".to_owned()).try_into().unwrap();
+        assert_eq!(label, "Level1");
+        let status: String = Value::Constant("LINK_1_GB".to_owned()).try_into().unwrap();
+        assert_eq!(status, "LINK_1_GB");
+
+        assert!(matches!(
+            f64::try_from(Value::Boolean(false)),
+            Err(Error::WrongValueType {
+                expected: "number",
+                got: "boolean"
+            })
+        ));
+        assert!(matches!(
+            String::try_from(Value::Array(Vec::new())),
+            Err(Error::WrongValueType {
+                expected: "string",
+                got: "array"
+            })
+        ));
+    }
+
+    #[test]
+    fn should_get_value_from_path() {
+        let source = include_str!("../tests/corpus/ok_value_network_status.ttp");
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp(source).unwrap()
+        else {
+            panic!("Network status sample is not a value");
+        };
+
+        assert_eq!(
+            value.get_path("networkInterfaceStatusWithName[0].networkInterfaceStatus.ip"),
+            Some(&Value::String("10.0.151.235".to_owned()))
+        );
+        assert_eq!(
+            value.get_path("dnsStatus.primaryDNSServer"),
+            Some(&Value::String("10.0.148.1".to_owned()))
+        );
+        assert_eq!(value.get_path("schemaVersion"), Some(&Value::Integer(2)));
+
+        assert_eq!(value.get_path("dnsStatus.missing"), None); // Missing key
+        assert_eq!(value.get_path("networkInterfaceStatusWithName[1]"), None); // Out of range
+        assert_eq!(value.get_path("dnsStatus[0]"), None); // Not an array
+        assert_eq!(value.get_path("schemaVersion.value"), None); // Not a map
+        assert_eq!(value.get_path("networkInterfaceStatusWithName[a]"), None); // Invalid index
+    }
+
     #[test]
     fn should_parse_err() {
         assert_eq!(
@@ -580,7 +1863,12 @@ mod test {
             .unwrap(),
             Response::Err(ErrResponse {
                 message: "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
-                    .to_owned()
+                    .to_owned(),
+                body: Some(Box::new(Value::Map(ValueMap::from_iter([
+                    ("deviceId".to_owned(), Value::Integer(0)),
+                    ("classCode".to_owned(), Value::Integer(0)),
+                    ("instanceNum".to_owned(), Value::Integer(0)),
+                ])))),
             })
         );
         assert_eq!(
@@ -588,15 +1876,22 @@ mod test {
                 "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}\nAAAAA"
             )
             .unwrap(),
+            Response::Err(ErrResponse::new(
+                "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
+            ))
+        );
+        assert_eq!(
+            Response::parse_ttp("-ERR").unwrap(),
             Response::Err(ErrResponse {
-                message: "address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}"
-                    .to_owned()
+                message: "".to_owned(),
+                body: None,
             })
         );
         assert_eq!(
-            Response::parse_ttp("-ERR").unwrap(),
+            Response::parse_ttp("-ERR cannot recall preset {1001").unwrap(),
             Response::Err(ErrResponse {
-                message: "".to_owned()
+                message: "cannot recall preset {1001".to_owned(),
+                body: None,
             })
         );
     }
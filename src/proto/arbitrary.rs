@@ -0,0 +1,78 @@
+//! Property tests asserting responses survive serialization and parsing unchanged
+//!
+//! Generated values stay within what devices send: numbers have six decimals,
+//! constants start with an uppercase letter so they are not mistaken for numbers or booleans.
+
+use proptest::{
+    arbitrary::Arbitrary,
+    collection::vec,
+    prelude::*,
+    strategy::{BoxedStrategy, Just},
+};
+
+use super::{ErrResponse, OkResponse, PublishToken, Response, Value, ValueMap};
+
+const KEY: &str = "[a-z][a-zA-Z0-9]{0,8}";
+
+impl Arbitrary for Value {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        let leaf = prop_oneof![
+            // Devices send six fixed decimals, only those numbers are represented exactly
+            (-1_000_000_000_000_i64..1_000_000_000_000)
+                .prop_map(|it| Value::Number(it as f64 / 1_000_000.0)),
+            any::<i64>().prop_map(Value::Integer),
+            any::<bool>().prop_map(Value::Boolean),
+            "[ -~\n]{0,16}".prop_map(Value::String),
+            "[A-Z][a-zA-Z0-9_.-]{0,12}".prop_map(Value::Constant),
+        ];
+        leaf.prop_recursive(3, 32, 4, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..4).prop_map(Value::Array),
+                vec((KEY, inner), 0..4).prop_map(|fields| Value::Map(ValueMap::from_iter(fields))),
+            ]
+        })
+        .boxed()
+    }
+}
+
+impl Arbitrary for Response {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(Response::Ok(OkResponse::Ok)),
+            any::<Value>().prop_map(|it| Response::Ok(OkResponse::WithValue(it))),
+            vec(any::<Value>(), 0..4).prop_map(|it| Response::Ok(OkResponse::WithList(it))),
+            // Padding around message is not kept by parser
+            "([!-~]([ -~]{0,30}[!-~])?)?".prop_map(|it| Response::Err(ErrResponse::new(it))),
+            ("[ -~]{0,16}", any::<Value>()).prop_map(|(label, value)| {
+                Response::PublishToken(PublishToken { label, value })
+            }),
+        ]
+        .boxed()
+    }
+}
+
+mod test {
+    use proptest::prelude::*;
+
+    use crate::proto::{IntoTTP, Response, Value, parser::parse_value};
+
+    proptest! {
+        #[test]
+        fn should_round_trip_value(value: Value) {
+            let serialized = value.clone().into_ttp();
+            prop_assert_eq!(parse_value(&serialized).map(|it| it.1), Ok(value));
+        }
+
+        #[test]
+        fn should_round_trip_response(response: Response) {
+            let serialized = response.clone().into_ttp();
+            prop_assert_eq!(Response::parse_ttp(&serialized), Ok(response));
+        }
+    }
+}
@@ -1,22 +1,73 @@
 //! Constants related to commands
 
 /// "get" command string
-pub const COMMAND_GET:&str = "get";
+pub const COMMAND_GET: &str = "get";
 
 /// "set" command string
-pub const COMMAND_SET:&str = "set";
+pub const COMMAND_SET: &str = "set";
 
 /// "increment" command string
-pub const COMMAND_INCREMENT:&str = "increment";
+pub const COMMAND_INCREMENT: &str = "increment";
 
 /// "decrement" command string
-pub const COMMAND_DECREMENT:&str = "decrement";
+pub const COMMAND_DECREMENT: &str = "decrement";
 
 /// "toggle" command string
-pub const COMMAND_TOGGLE:&str = "toggle";
+pub const COMMAND_TOGGLE: &str = "toggle";
 
 /// "subscribe" command string
-pub const COMMAND_SUBSCRIBE:&str = "subscribe";
+pub const COMMAND_SUBSCRIBE: &str = "subscribe";
 
 /// "unsubscribe" command string
-pub const COMMAND_UNSUBSCRIBE:&str = "unsubscribe";
+pub const COMMAND_UNSUBSCRIBE: &str = "unsubscribe";
+
+/// "dial" telephony command string
+pub const COMMAND_DIAL: &str = "dial";
+
+/// "speedDial" telephony command string
+pub const COMMAND_SPEED_DIAL: &str = "speedDial";
+
+/// "redial" telephony command string
+pub const COMMAND_REDIAL: &str = "redial";
+
+/// "end" telephony command string
+pub const COMMAND_END: &str = "end";
+
+/// "flash" telephony command string
+pub const COMMAND_FLASH: &str = "flash";
+
+/// "send" telephony command string
+pub const COMMAND_SEND: &str = "send";
+
+/// "dtmf" telephony command string
+pub const COMMAND_DTMF: &str = "dtmf";
+
+/// "answer" telephony command string
+pub const COMMAND_ANSWER: &str = "answer";
+
+/// "lconf" telephony command string
+pub const COMMAND_LCONF: &str = "lconf";
+
+/// "resume" telephony command string
+pub const COMMAND_RESUME: &str = "resume";
+
+/// "hold" telephony command string
+pub const COMMAND_HOLD: &str = "hold";
+
+/// "offHook" telephony command string
+pub const COMMAND_OFF_HOOK: &str = "offHook";
+
+/// "onHook" telephony command string
+pub const COMMAND_ON_HOOK: &str = "onHook";
+
+/// "recallPreset" device command string
+pub const COMMAND_RECALL_PRESET: &str = "recallPreset";
+
+/// "recallPresetByName" device command string
+pub const COMMAND_RECALL_PRESET_BY_NAME: &str = "recallPresetByName";
+
+/// "reboot" device command string
+pub const COMMAND_REBOOT: &str = "reboot";
+
+/// "bye" session command string, device ends the session
+pub const COMMAND_BYE: &str = "bye";
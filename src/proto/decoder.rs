@@ -0,0 +1,86 @@
+//! Push-based decoding of responses from byte chunks
+//!
+//! [ResponseDecoder] does not read from any stream, bytes are fed as they arrive
+//! and complete responses are returned once their line ends.
+
+use super::{Error, Response};
+
+/// Decoder buffering partial lines until a response is complete
+///
+/// Like sessions, blank lines and echoes of commands are skipped.
+/// Multi-line error responses are not gathered, each line is decoded on its own.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl ResponseDecoder {
+    /// Create a decoder with an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed received bytes, returning responses of every line completed by them
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Result<Response, Error>> {
+        self.buffer.extend_from_slice(bytes);
+        let Some(last_newline) = self.buffer.iter().rposition(|it| *it == b'\n') else {
+            return Vec::new();
+        };
+
+        let responses = self.buffer[..last_newline]
+            .split(|it| *it == b'\n')
+            .filter_map(|line| {
+                let line = String::from_utf8_lossy(line);
+                let line = line.trim();
+                line.starts_with(['+', '-', '!'])
+                    .then(|| Response::parse_ttp(line))
+            })
+            .collect();
+        self.buffer.drain(..=last_newline);
+        responses
+    }
+
+    /// Bytes of the line not terminated yet
+    pub fn pending(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::ResponseDecoder;
+    use crate::proto::{ErrResponse, OkResponse, PublishToken, Response, Value};
+
+    #[test]
+    fn should_decode_responses_split_across_chunks() {
+        let mut decoder = ResponseDecoder::new();
+        assert_eq!(decoder.feed(b"Level3 get level 1\r\n+OK \"val"), vec![]);
+        assert_eq!(decoder.pending(), b"+OK \"val");
+        assert_eq!(
+            decoder.feed(b"ue\":-10.000000\r\n\n! \"publishToken\":\"MyLevel\" \"value\":-8\n-ERR"),
+            vec![
+                Ok(Response::Ok(OkResponse::WithValue(Value::Number(-10.0)))),
+                Ok(Response::PublishToken(PublishToken {
+                    label: "MyLevel".to_owned(),
+                    value: Value::Integer(-8)
+                })),
+            ]
+        );
+        assert_eq!(
+            decoder.feed(b" address not found\n"),
+            vec![Ok(Response::Err(ErrResponse::new("address not found")))]
+        );
+        assert!(decoder.pending().is_empty());
+    }
+
+    #[test]
+    fn should_report_invalid_line_and_continue() {
+        let mut decoder = ResponseDecoder::new();
+        let responses = decoder.feed(b"! garbage\n+OK\n");
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].is_err());
+        assert_eq!(responses[1], Ok(Response::Ok(OkResponse::Ok)));
+    }
+}
@@ -0,0 +1,139 @@
+//! Typed active faults of a device
+//!
+//! Parsed from the `DEVICE get activeFaultList` response, a list of fault indicators each
+//! holding the faults it reports. Fields unknown to this crate are kept as strings.
+
+use std::collections::BTreeMap;
+
+use super::{Error, FromTTP, Value, ValueMap, take_key};
+
+/// Severity of a fault, given by the indicator reporting it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaultSeverity {
+    /// Indicator reports no fault
+    None,
+    /// Minor fault, device keeps running
+    Minor,
+    /// Major fault
+    Major,
+    /// Indicator unknown to this crate, holding its identifier
+    Unknown(String),
+}
+
+impl FaultSeverity {
+    /// Read severity from an indicator identifier, such as `INDICATOR_MAJOR_IN_DEVICE`
+    pub fn from_indicator(indicator: &str) -> Self {
+        if indicator.starts_with("INDICATOR_NONE") {
+            FaultSeverity::None
+        } else if indicator.starts_with("INDICATOR_MINOR") {
+            FaultSeverity::Minor
+        } else if indicator.starts_with("INDICATOR_MAJOR") {
+            FaultSeverity::Major
+        } else {
+            FaultSeverity::Unknown(indicator.to_owned())
+        }
+    }
+}
+
+/// An active fault of a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultStatus {
+    /// Fault identifier, such as `FAULT_DANTE_FLOW_INACTIVE`
+    pub id: String,
+    /// Human readable description
+    pub name: String,
+    /// Severity of the indicator reporting fault
+    pub severity: FaultSeverity,
+    /// Fault fields unknown to this crate, as sent by device
+    pub other: BTreeMap<String, String>,
+}
+
+/// Parse an active fault list into its faults, indicators without fault are skipped
+///
+/// A single indicator map is accepted as well as a list of indicators.
+pub fn parse_fault_list(value: Value) -> Result<Vec<FaultStatus>, Error> {
+    let indicators = match value {
+        Value::Map(indicator) => vec![indicator],
+        value => Vec::<ValueMap>::from_ttp_value(value)?,
+    };
+
+    let mut faults = Vec::new();
+    for mut indicator in indicators {
+        let severity = FaultSeverity::from_indicator(&take_key::<String>(&mut indicator, "id")?);
+        let entries: Vec<ValueMap> = match take_key(&mut indicator, "faults") {
+            Err(Error::MissingKey(_)) => Vec::new(),
+            entries => entries?,
+        };
+        for mut entry in entries {
+            faults.push(FaultStatus {
+                id: take_key(&mut entry, "id")?,
+                name: take_key(&mut entry, "name")?,
+                severity: severity.clone(),
+                other: entry
+                    .into_iter()
+                    .map(|(key, value)| match value {
+                        Value::String(v) | Value::Constant(v) => (key, v),
+                        value => (key, value.to_string()),
+                    })
+                    .collect(),
+            });
+        }
+    }
+    Ok(faults)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{FaultSeverity, FaultStatus, parse_fault_list};
+    use crate::proto::{Error, OkResponse, Response};
+
+    #[test]
+    fn should_parse_fault_list() {
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp("+OK \"value\":[{\"id\":INDICATOR_MAJOR_IN_DEVICE \"name\":\"Major Fault in Device\" \"faults\":[{\"id\":FAULT_DANTE_FLOW_INACTIVE \"name\":\"one or more Dante flows inactive\" \"channel\":2}] \"serialNumber\":\"03180254\"} {\"id\":INDICATOR_NONE_IN_SYSTEM \"name\":\"No fault in system\" \"faults\":[]}]").unwrap() else {
+            panic!("Expected a value")
+        };
+
+        assert_eq!(
+            parse_fault_list(value).unwrap(),
+            vec![FaultStatus {
+                id: "FAULT_DANTE_FLOW_INACTIVE".to_owned(),
+                name: "one or more Dante flows inactive".to_owned(),
+                severity: FaultSeverity::Major,
+                other: BTreeMap::from([("channel".to_owned(), "2".to_owned())]),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_keep_unknown_fault_indicators() {
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp("+OK \"value\":{\"id\":INDICATOR_CRITICAL \"name\":\"Critical\" \"faults\":[{\"id\":FAULT_FAN \"name\":\"Fan stopped\"}]}").unwrap() else {
+            panic!("Expected a value")
+        };
+
+        let faults = parse_fault_list(value).unwrap();
+        assert_eq!(
+            faults[0].severity,
+            FaultSeverity::Unknown("INDICATOR_CRITICAL".to_owned())
+        );
+        assert!(faults[0].other.is_empty());
+    }
+
+    #[test]
+    fn should_fail_on_fault_without_name() {
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp(
+            "+OK \"value\":[{\"id\":INDICATOR_MINOR_IN_DEVICE \"faults\":[{\"id\":FAULT_FAN}]}]",
+        )
+        .unwrap() else {
+            panic!("Expected a value")
+        };
+
+        assert!(matches!(
+            parse_fault_list(value),
+            Err(Error::MissingKey(key)) if key == "name"
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+//! Typed network status of a device
+//!
+//! Parsed from the `DEVICE get networkStatus` response, keys unknown to this crate are ignored
+//! so that firmware additions do not break parsing.
+
+use super::{Error, FromTTP, Value, ValueMap, take_key};
+
+/// Network status of a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceNetworkStatus {
+    /// Hostname of device
+    pub hostname: String,
+    /// Default gateway in use
+    pub default_gateway: String,
+    /// Status of each network interface
+    pub interfaces: Vec<NetworkInterfaceStatus>,
+    /// Configured DNS servers, empty entries are omitted
+    pub dns_servers: Vec<String>,
+    /// DNS domain name
+    pub domain_name: String,
+    /// Whether mDNS is enabled
+    pub mdns_enabled: bool,
+    /// Whether Telnet access is disabled
+    pub telnet_disabled: bool,
+    /// Whether SSH access is disabled
+    pub ssh_disabled: bool,
+}
+
+/// Status of a network interface of a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkInterfaceStatus {
+    /// Name of interface, such as `control`
+    pub interface_id: String,
+    /// MAC address
+    pub mac_address: String,
+    /// IP address
+    pub ip: String,
+    /// Network mask
+    pub netmask: String,
+    /// Gateway of interface
+    pub gateway: String,
+    /// Link status, such as `LINK_1_GB`
+    pub link_status: String,
+}
+
+impl FromTTP for DeviceNetworkStatus {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        let mut map = ValueMap::from_ttp_value(value)?;
+        let mut dns: ValueMap = take_key(&mut map, "dnsStatus")?;
+        Ok(DeviceNetworkStatus {
+            hostname: take_key(&mut map, "hostname")?,
+            default_gateway: take_key(&mut map, "defaultGatewayStatus")?,
+            interfaces: take_key(&mut map, "networkInterfaceStatusWithName")?,
+            dns_servers: ["primaryDNSServer", "secondaryDNSServer"]
+                .into_iter()
+                .map(|key| take_key::<String>(&mut dns, key))
+                .filter(|it| !matches!(it, Ok(server) if server.is_empty()))
+                .collect::<Result<_, _>>()?,
+            domain_name: take_key(&mut dns, "domainName")?,
+            mdns_enabled: take_key(&mut map, "mDNSEnabled")?,
+            telnet_disabled: take_key(&mut map, "telnetDisabled")?,
+            ssh_disabled: take_key(&mut map, "sshDisabled")?,
+        })
+    }
+}
+
+impl TryFrom<Value> for DeviceNetworkStatus {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::from_ttp_value(value)
+    }
+}
+
+impl FromTTP for NetworkInterfaceStatus {
+    fn from_ttp_value(value: Value) -> Result<Self, Error> {
+        let mut map = ValueMap::from_ttp_value(value)?;
+        let mut status: ValueMap = take_key(&mut map, "networkInterfaceStatus")?;
+        Ok(NetworkInterfaceStatus {
+            interface_id: take_key(&mut map, "interfaceId")?,
+            mac_address: take_key(&mut status, "macAddress")?,
+            ip: take_key(&mut status, "ip")?,
+            netmask: take_key(&mut status, "netmask")?,
+            gateway: take_key(&mut status, "gateway")?,
+            link_status: take_key(&mut status, "linkStatus")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{DeviceNetworkStatus, NetworkInterfaceStatus};
+    use crate::proto::{Error, OkResponse, Response};
+
+    #[test]
+    fn should_parse_network_status() {
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp("+OK \"value\":{\"schemaVersion\":2 \"hostname\":\"TesiraForte05953601\" \"defaultGatewayStatus\":\"0.0.0.0\" \"networkInterfaceStatusWithName\":[{\"interfaceId\":\"control\" \"networkInterfaceStatus\":{\"macAddress\":\"78:45:01:3d:86:92\" \"linkStatus\":LINK_1_GB \"addressSource\":DHCP \"ip\":\"10.0.151.235\" \"netmask\":\"255.255.252.0\" \"dhcpLeaseObtainedDate\":\"Wed Jun 26 16:45:27 UTC 2024\" \"dhcpLeaseExpiresDate\":\"Thu Jun 27 16:45:27 UTC 2024\" \"gateway\":\"10.0.148.1\"}}] \"dnsStatus\":{\"primaryDNSServer\":\"10.0.148.1\" \"secondaryDNSServer\":\"\" \"domainName\":\"\"} \"mDNSEnabled\":true \"telnetDisabled\":true \"sshDisabled\":false \"networkPortMode\":PORT_MODE_SEPARATE \"rstpEnabled\":false \"httpsEnabled\":false \"igmpEnabled\":false \"switchPortMode\":SWITCH_PORT_MODE_CONTROL_AND_MEDIA}").unwrap() else {
+            panic!("Expected a value")
+        };
+
+        assert_eq!(
+            DeviceNetworkStatus::try_from(value).unwrap(),
+            DeviceNetworkStatus {
+                hostname: "TesiraForte05953601".to_owned(),
+                default_gateway: "0.0.0.0".to_owned(),
+                interfaces: vec![NetworkInterfaceStatus {
+                    interface_id: "control".to_owned(),
+                    mac_address: "78:45:01:3d:86:92".to_owned(),
+                    ip: "10.0.151.235".to_owned(),
+                    netmask: "255.255.252.0".to_owned(),
+                    gateway: "10.0.148.1".to_owned(),
+                    link_status: "LINK_1_GB".to_owned(),
+                }],
+                dns_servers: vec!["10.0.148.1".to_owned()],
+                domain_name: "".to_owned(),
+                mdns_enabled: true,
+                telnet_disabled: true,
+                ssh_disabled: false,
+            }
+        );
+    }
+
+    #[test]
+    fn should_fail_on_missing_network_status_key() {
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp("+OK \"value\":{\"defaultGatewayStatus\":\"0.0.0.0\" \"networkInterfaceStatusWithName\":[] \"dnsStatus\":{\"primaryDNSServer\":\"\" \"secondaryDNSServer\":\"\" \"domainName\":\"\"} \"mDNSEnabled\":true \"telnetDisabled\":true \"sshDisabled\":false}").unwrap() else {
+            panic!("Expected a value")
+        };
+
+        assert!(matches!(
+            DeviceNetworkStatus::try_from(value),
+            Err(Error::MissingKey(key)) if key == "hostname"
+        ));
+    }
+}
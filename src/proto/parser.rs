@@ -1,70 +1,87 @@
 //! Parsers for Tesira Text Protocol responses and values
 
-use std::collections::HashMap;
-
 use nom::{
     IResult, Parser,
     branch::alt,
     bytes::complete::{is_not, tag, take_until, take_while1},
-    character::complete::space1,
-    combinator::{opt, rest, value},
-    multi::separated_list0,
+    character::complete::{digit1, one_of, space0, space1},
+    combinator::{all_consuming, map_res, not, opt, recognize, rest, value},
+    multi::{many0, separated_list0},
     sequence::{delimited, pair, preceded, terminated},
 };
 
-use super::{ErrResponse, OkResponse, PublishToken, Response, Value};
+use super::{ErrResponse, OkResponse, PublishToken, Response, Value, ValueMap};
+
+fn integer_str(input: &str) -> IResult<&str, i64> {
+    map_res(
+        terminated(
+            recognize(pair(opt(one_of("+-")), digit1)),
+            // Numbers with a fractional part or an exponent are floats
+            not(alt((
+                tag("."),
+                recognize(pair(one_of("eE"), opt(one_of("+-")))),
+            ))),
+        ),
+        str::parse::<i64>,
+    )
+    .parse(input)
+}
 
 fn float_str(input: &str) -> IResult<&str, f64> {
-    pair(
-        pair(opt(tag("-")), take_while1(|c: char| c.is_ascii_digit())),
-        opt(preceded(
-            tag("."),
-            take_while1(|c: char| c.is_ascii_digit()),
+    // The whole literal is parsed at once so that the result is correctly rounded
+    map_res(
+        recognize((
+            opt(one_of("+-")),
+            alt((
+                recognize(pair(digit1, opt(preceded(tag("."), digit1)))),
+                // Integer part may be omitted when a fractional part is present
+                recognize(preceded(tag("."), digit1)),
+            )),
+            opt(recognize((one_of("eE"), opt(one_of("+-")), digit1))),
         )),
+        str::parse::<f64>,
     )
-    .map(|it: ((Option<&str>, &str), Option<&str>)| {
-        let mut whole: i64 = it.0.1.parse().unwrap();
-        if it.0.0.is_some() {
-            whole *= -1
-        }
-
-        let fractional =
-            it.1.map(|it: &str| {
-                let trimmed_value = it.trim_end_matches('0');
-                if trimmed_value.is_empty() {
-                    return 0_f64;
-                }
-                let mut value: i64 = trimmed_value.parse().unwrap();
-                if whole < 0 {
-                    value *= -1
-                }
-                value as f64 / (10_i64.pow(trimmed_value.len() as u32)) as f64
-            })
-            .unwrap_or(0_f64);
-
-        whole as f64 + fractional
-    })
     .parse(input)
 }
 
 fn delimited_str(input: &str) -> IResult<&str, String> {
-    delimited(tag("\""), take_until("\""), tag("\""))
-        .map(|it: &str| it.to_owned())
-        .parse(input)
+    let (content, _) = tag("\"").parse(input)?;
+    let mut value = String::new();
+    let mut chars = content.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((&content[i + 1..], value)),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, escaped @ ('"' | '\\'))) => value.push(escaped),
+                Some((_, other)) => {
+                    // Unknown escapes are kept as is
+                    value.push('\\');
+                    value.push(other);
+                }
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err(nom::Err::Error(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::TakeUntil,
+    )))
 }
 
 fn ttp_value(input: &str) -> IResult<&str, Value> {
     alt((
         delimited(
-            tag("{"),
+            pair(tag("{"), space0),
             separated_list0(
                 space1,
                 pair(delimited(tag("\""), is_not("\""), tag("\":")), ttp_value),
             ),
-            tag("}"),
+            pair(space0, tag("}")),
         )
         .map(|it| {
-            Value::Map(HashMap::from_iter(
+            Value::Map(ValueMap::from_iter(
                 it.into_iter().map(|it| (it.0.to_owned(), it.1)),
             ))
         }), // Map
@@ -72,15 +89,26 @@ fn ttp_value(input: &str) -> IResult<&str, Value> {
         delimited_str.map(Value::String),           // String
         value(Value::Boolean(true), tag("true")),   // Boolean true
         value(Value::Boolean(false), tag("false")), // Boolean false
+        integer_str.map(Value::Integer),            // Integer number
         float_str.map(Value::Number),               // Floating point number
-        take_while1(|it: char| it.is_alphanumeric() || it == '_')
-            .map(|it: &str| Value::Constant(it.to_owned())),
+        take_while1(|it: char| it.is_alphanumeric() || matches!(it, '_' | '-' | '.'))
+            .map(|it: &str| Value::Constant(it.to_owned())), // Constant such as Linkwitz-Riley
     ))
     .parse(input)
 }
 
+/// Parse a single value, the whole input must be consumed
+pub fn parse_value(input: &str) -> IResult<&str, Value> {
+    all_consuming(ttp_value).parse(input)
+}
+
 fn ttp_list_of_values(input: &str) -> IResult<&str, Vec<Value>> {
-    delimited(tag("["), separated_list0(space1, ttp_value), tag("]")).parse(input)
+    delimited(
+        pair(tag("["), space0),
+        separated_list0(space1, ttp_value),
+        pair(space0, tag("]")),
+    )
+    .parse(input)
 }
 
 fn field(name: &str) -> impl Parser<&str, Output = &str, Error = nom::error::Error<&str>> {
@@ -101,23 +129,29 @@ fn ok_response(input: &str) -> IResult<&str, OkResponse> {
 }
 
 fn err_response(input: &str) -> IResult<&str, ErrResponse> {
-    let (input, message) = preceded(
+    let (input, (message, continuation)) = preceded(
         tag("-ERR"),
-        opt(preceded(space1, alt((take_until("\n"), rest)))),
+        pair(
+            opt(preceded(space1, alt((take_until("\n"), rest)))),
+            // Indented lines following the error are part of its message
+            many0(preceded(tag("\n"), recognize(pair(space1, is_not("\n"))))),
+        ),
     )
     .parse(input)?;
 
-    Ok((
-        input,
-        ErrResponse {
-            message: message.unwrap_or("").to_owned(),
-        },
-    ))
+    // Padding before line terminators is not part of the message
+    let mut message = message.unwrap_or("").trim_end().to_owned();
+    for line in continuation {
+        message.push('\n');
+        message.push_str(line.trim_end());
+    }
+
+    Ok((input, ErrResponse::new(message)))
 }
 
 fn publish_token_response(input: &str) -> IResult<&str, PublishToken> {
     let (input, (label, value)) = preceded(
-        tag("! \"publishToken\":"),
+        (tag("!"), space1, tag("\"publishToken\":")),
         pair(
             delimited_str,
             preceded(space1, preceded(field("value"), ttp_value)),
@@ -146,7 +180,10 @@ pub fn parse_response(input: &str) -> IResult<&str, Response> {
 
 mod test {
     #[allow(unused_imports)]
-    use crate::proto::parser::float_str;
+    use crate::proto::{
+        ErrResponse, Value,
+        parser::{delimited_str, err_response, float_str, integer_str, parse_value, ttp_value},
+    };
 
     #[test]
     fn should_parse_float() {
@@ -157,5 +194,123 @@ mod test {
         assert_eq!(float_str("5.2000000000"), Ok(("", 5.2_f64)));
         assert_eq!(float_str("12"), Ok(("", 12.0_f64)));
         assert_eq!(float_str("12.000"), Ok(("", 12.0_f64)));
+        assert_eq!(float_str("+5"), Ok(("", 5.0_f64)));
+        assert_eq!(float_str("+2.5e1"), Ok(("", 25.0_f64)));
+        assert_eq!(
+            float_str("12345678901234567890"),
+            Ok(("", 12_345_678_901_234_567_890.0_f64))
+        );
+        assert_eq!(
+            float_str("0.1234567890123456789012"),
+            Ok(("", 0.123_456_789_012_345_68_f64))
+        );
+        assert_eq!(ttp_value("+5"), Ok(("", Value::Integer(5))));
+    }
+
+    #[test]
+    fn should_parse_integer() {
+        assert_eq!(integer_str("0"), Ok(("", 0)));
+        assert_eq!(integer_str("-15"), Ok(("", -15)));
+        assert_eq!(integer_str("+5"), Ok(("", 5)));
+        assert_eq!(integer_str("3 4"), Ok((" 4", 3)));
+        assert_eq!(
+            integer_str("9007199254740993"), // Not representable as f64
+            Ok(("", 9_007_199_254_740_993))
+        );
+        assert!(integer_str("12.000").is_err());
+        assert!(integer_str("2E3").is_err());
+        assert!(integer_str("99999999999999999999").is_err()); // Parsed as a float
+    }
+
+    #[test]
+    fn should_parse_negative_float_below_one() {
+        assert_eq!(float_str("-0.5"), Ok(("", -0.5_f64)));
+        assert_eq!(float_str("-0.25"), Ok(("", -0.25_f64)));
+        assert_eq!(float_str("-0.500000"), Ok(("", -0.5_f64)));
+        let (_, zero) = float_str("-0.0").unwrap();
+        assert_eq!(zero, 0.0_f64);
+        assert!(zero.is_sign_negative());
+    }
+
+    #[test]
+    fn should_parse_float_with_exponent() {
+        assert_eq!(float_str("1.5e-05"), Ok(("", 1.5e-05_f64)));
+        assert_eq!(float_str("2E3"), Ok(("", 2000.0_f64)));
+        assert_eq!(float_str("-4.2e+1"), Ok(("", -42.0_f64)));
+        assert_eq!(float_str("12e"), Ok(("e", 12.0_f64))); // Not an exponent
+        assert_eq!(float_str("2.3e2"), Ok(("", 230.0_f64)));
+        assert_eq!(float_str("1e99999999999"), Ok(("", f64::INFINITY)));
+        assert_eq!(float_str("1e-99999999999"), Ok(("", 0.0_f64)));
+    }
+
+    #[test]
+    fn should_parse_float_without_integer_part() {
+        assert_eq!(float_str(".5"), Ok(("", 0.5_f64)));
+        assert_eq!(float_str("-.5"), Ok(("", -0.5_f64)));
+        assert_eq!(float_str(".25e1"), Ok(("", 2.5_f64)));
+        assert_eq!(float_str("5."), Ok((".", 5.0_f64))); // Dot without digits is not a fraction
+        assert!(float_str(".").is_err());
+        assert!(parse_value("5.").is_err());
+        assert_eq!(parse_value("-.5"), Ok(("", Value::Number(-0.5))));
+    }
+
+    #[test]
+    fn should_parse_constant_with_hyphen_and_dot() {
+        assert_eq!(
+            ttp_value("{\"type\":Linkwitz-Riley \"version\":v4.2}"),
+            Ok((
+                "",
+                Value::Map(
+                    [
+                        (
+                            "type".to_owned(),
+                            Value::Constant("Linkwitz-Riley".to_owned())
+                        ),
+                        ("version".to_owned(), Value::Constant("v4.2".to_owned()))
+                    ]
+                    .into()
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn should_parse_escaped_string() {
+        assert_eq!(
+            delimited_str("\"a \\\"quoted\\\" word\" next"),
+            Ok((" next", "a \"quoted\" word".to_owned()))
+        );
+        assert_eq!(
+            delimited_str("\"two\\nlines\""),
+            Ok(("", "two\nlines".to_owned()))
+        );
+        assert_eq!(
+            delimited_str("\"C:\\\\presets\\\\\""), // Trailing escaped backslash
+            Ok(("", "C:\\presets\\".to_owned()))
+        );
+        assert_eq!(
+            delimited_str("\"C:\\presets\""), // Unknown escape
+            Ok(("", "C:\\presets".to_owned()))
+        );
+        assert_eq!(delimited_str("\"\""), Ok(("", String::new())));
+        assert!(delimited_str("\"unterminated\\\"").is_err());
+    }
+
+    #[test]
+    fn should_parse_multiline_err() {
+        assert_eq!(
+            err_response("-ERR cannot recall preset\n  preset 1001 not found\n+OK"),
+            Ok((
+                "\n+OK",
+                ErrResponse::new("cannot recall preset\n  preset 1001 not found")
+            ))
+        );
+        assert_eq!(
+            err_response("-ERR cannot recall preset\n\n  not an error line"),
+            Ok((
+                "\n\n  not an error line",
+                ErrResponse::new("cannot recall preset")
+            ))
+        );
     }
 }
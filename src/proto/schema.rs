@@ -0,0 +1,212 @@
+//! Lightweight validation of value shapes
+//!
+//! Firmware updates may change the shape of structured responses, validating a value against
+//! a [ValueSchema] before extracting fields gives a clear error about what changed.
+
+use thiserror::Error;
+
+use super::Value;
+
+/// Expected shape of a [Value]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueSchema {
+    /// Any value is accepted
+    Any,
+    /// A [Value::Number] or a [Value::Integer]
+    Number,
+    /// A [Value::Integer]
+    Integer,
+    /// A [Value::Boolean]
+    Boolean,
+    /// A [Value::String]
+    String,
+    /// A [Value::Constant]
+    Constant,
+    /// A [Value::Array] where every element conforms to the schema
+    Array(Box<ValueSchema>),
+    /// A [Value::Map] containing at least the given keys, extra keys are allowed
+    Map(Vec<(String, ValueSchema)>),
+    /// A map entry that may be missing, when present it conforms to the schema
+    Optional(Box<ValueSchema>),
+}
+
+impl ValueSchema {
+    /// Schema of an array where every element conforms to `item`
+    pub fn array(item: ValueSchema) -> Self {
+        Self::Array(Box::new(item))
+    }
+
+    /// Schema of a map containing at least the given keys
+    pub fn map(fields: impl IntoIterator<Item = (impl Into<String>, ValueSchema)>) -> Self {
+        Self::Map(
+            fields
+                .into_iter()
+                .map(|(key, schema)| (key.into(), schema))
+                .collect(),
+        )
+    }
+
+    /// Schema of a map entry that may be missing
+    pub fn optional(schema: ValueSchema) -> Self {
+        Self::Optional(Box::new(schema))
+    }
+
+    /// Schema of device information read with `DEVICE get networkStatus`
+    pub fn device_info() -> Self {
+        Self::map([
+            ("schemaVersion", Self::Number),
+            ("hostname", Self::String),
+            ("defaultGatewayStatus", Self::String),
+            (
+                "networkInterfaceStatusWithName",
+                Self::optional(Self::array(Self::map([
+                    ("interfaceId", Self::String),
+                    ("networkInterfaceStatus", Self::Any),
+                ]))),
+            ),
+            ("mDNSEnabled", Self::Boolean),
+            ("telnetDisabled", Self::Boolean),
+            ("sshDisabled", Self::Boolean),
+        ])
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            ValueSchema::Any => "any",
+            ValueSchema::Number => "number",
+            ValueSchema::Integer => "integer",
+            ValueSchema::Boolean => "boolean",
+            ValueSchema::String => "string",
+            ValueSchema::Constant => "constant",
+            ValueSchema::Array(_) => "array",
+            ValueSchema::Map(_) => "map",
+            ValueSchema::Optional(schema) => schema.type_name(),
+        }
+    }
+}
+
+/// A value does not conform to its schema
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SchemaError {
+    /// A required map key is missing
+    #[error("Missing key at '{0}'")]
+    MissingKey(String),
+    /// A value is not of the expected type
+    #[error("Wrong value type at '{path}': expected {expected}, got {got}")]
+    WrongValueType {
+        /// Path of the value, see [Value::validate]
+        path: String,
+        /// Expected value type
+        expected: &'static str,
+        /// Actual value type
+        got: &'static str,
+    },
+}
+
+impl Value {
+    /// Check this value conforms to a schema
+    ///
+    /// Errors report the path of the offending value with dotted map keys and bracketed
+    /// array indexes, such as `interfaces[0].ip`.
+    pub fn validate(&self, schema: &ValueSchema) -> Result<(), SchemaError> {
+        self.validate_at(schema, "")
+    }
+
+    fn validate_at(&self, schema: &ValueSchema, path: &str) -> Result<(), SchemaError> {
+        match (schema, self) {
+            (ValueSchema::Any, _)
+            | (ValueSchema::Number, Value::Number(_) | Value::Integer(_))
+            | (ValueSchema::Integer, Value::Integer(_))
+            | (ValueSchema::Boolean, Value::Boolean(_))
+            | (ValueSchema::String, Value::String(_))
+            | (ValueSchema::Constant, Value::Constant(_)) => Ok(()),
+            (ValueSchema::Optional(schema), value) => value.validate_at(schema, path),
+            (ValueSchema::Array(item), Value::Array(values)) => {
+                for (index, value) in values.iter().enumerate() {
+                    value.validate_at(item, &format!("{path}[{index}]"))?;
+                }
+                Ok(())
+            }
+            (ValueSchema::Map(fields), Value::Map(values)) => {
+                for (key, field_schema) in fields {
+                    let field_path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{path}.{key}")
+                    };
+                    match values.get(key) {
+                        Some(value) => value.validate_at(field_schema, &field_path)?,
+                        None if matches!(field_schema, ValueSchema::Optional(_)) => {}
+                        None => return Err(SchemaError::MissingKey(field_path)),
+                    }
+                }
+                Ok(())
+            }
+            (schema, value) => Err(SchemaError::WrongValueType {
+                path: path.to_owned(),
+                expected: schema.type_name(),
+                got: value.type_name(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{SchemaError, ValueSchema};
+    use crate::proto::{OkResponse, Response, Value, ValueMap};
+
+    fn parse_value(source: &str) -> Value {
+        match Response::parse_ttp(source).unwrap() {
+            Response::Ok(OkResponse::WithValue(v)) => v,
+            r => panic!("Unexpected response {r:?}"),
+        }
+    }
+
+    #[test]
+    fn should_validate_conforming_value() {
+        let value = parse_value(
+            "+OK \"value\":{\"schemaVersion\":2 \"hostname\":\"TesiraForte05953601\" \"defaultGatewayStatus\":\"0.0.0.0\" \"mDNSEnabled\":true \"telnetDisabled\":true \"sshDisabled\":false \"rstpEnabled\":false \"httpsEnabled\":false \"igmpEnabled\":false}",
+        );
+        assert_eq!(value.validate(&ValueSchema::device_info()), Ok(()));
+
+        let value = parse_value(
+            "+OK \"value\":{\"schemaVersion\":2 \"hostname\":\"TesiraForte05953601\" \"defaultGatewayStatus\":\"0.0.0.0\" \"networkInterfaceStatusWithName\":[{\"interfaceId\":\"control\" \"networkInterfaceStatus\":{\"ip\":\"10.0.151.235\"}}] \"mDNSEnabled\":true \"telnetDisabled\":true \"sshDisabled\":false}",
+        );
+        assert_eq!(value.validate(&ValueSchema::device_info()), Ok(()));
+    }
+
+    #[test]
+    fn should_reject_non_conforming_value() {
+        let value = parse_value(
+            "+OK \"value\":{\"schemaVersion\":2 \"defaultGatewayStatus\":\"0.0.0.0\" \"mDNSEnabled\":true \"telnetDisabled\":true \"sshDisabled\":false}",
+        );
+        assert_eq!(
+            value.validate(&ValueSchema::device_info()),
+            Err(SchemaError::MissingKey("hostname".to_owned()))
+        );
+
+        let value = parse_value(
+            "+OK \"value\":{\"schemaVersion\":2 \"hostname\":\"TesiraForte05953601\" \"defaultGatewayStatus\":\"0.0.0.0\" \"networkInterfaceStatusWithName\":[{\"interfaceId\":control \"networkInterfaceStatus\":{}}] \"mDNSEnabled\":true \"telnetDisabled\":true \"sshDisabled\":false}",
+        );
+        assert_eq!(
+            value.validate(&ValueSchema::device_info()),
+            Err(SchemaError::WrongValueType {
+                path: "networkInterfaceStatusWithName[0].interfaceId".to_owned(),
+                expected: "string",
+                got: "constant"
+            })
+        );
+
+        assert_eq!(
+            Value::Map(ValueMap::new()).validate(&ValueSchema::array(ValueSchema::Any)),
+            Err(SchemaError::WrongValueType {
+                path: "".to_owned(),
+                expected: "array",
+                got: "map"
+            })
+        );
+    }
+}
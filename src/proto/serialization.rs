@@ -0,0 +1,254 @@
+//! Serde support of [Value], enabled with the `serde` feature
+//!
+//! Values map to their natural JSON-like form, except constants which are serialized as
+//! `{"$constant":"NAME"}` so they are not mistaken for strings when deserialized.
+//!
+//! Conversions with [serde_json::Value] are also provided, they don't keep this distinction.
+
+use std::fmt;
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{MapAccess, SeqAccess, Visitor},
+    ser::SerializeMap,
+};
+
+use super::{Error, Value, ValueMap};
+
+/// Key of the single entry map representing a [Value::Constant]
+pub const CONSTANT_KEY: &str = "$constant";
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Number(v) => serializer.serialize_f64(*v),
+            Value::Integer(v) => serializer.serialize_i64(*v),
+            Value::Boolean(v) => serializer.serialize_bool(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Constant(v) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(CONSTANT_KEY, v)?;
+                map.end()
+            }
+            Value::Map(v) => v.serialize(serializer),
+            Value::Array(v) => v.serialize(serializer),
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Tesira Text Protocol value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(i64::try_from(v)
+            .map(Value::Integer)
+            .unwrap_or(Value::Number(v as f64)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = ValueMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            values.insert(key, value);
+        }
+
+        if values.len() == 1
+            && let Some(Value::String(constant)) = values.get(CONSTANT_KEY)
+        {
+            return Ok(Value::Constant(constant.clone()));
+        }
+        Ok(Value::Map(values))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Constants become JSON strings, the conversion is lossy
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Number(v) => serde_json::Number::from_f64(v)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null), // NaN and infinities have no JSON form
+            Value::Integer(v) => serde_json::Value::Number(v.into()),
+            Value::Boolean(v) => serde_json::Value::Bool(v),
+            Value::String(v) | Value::Constant(v) => serde_json::Value::String(v),
+            Value::Map(v) => serde_json::Value::Object(
+                v.into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+            Value::Array(v) => serde_json::Value::Array(v.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+
+/// JSON strings always become [Value::String], `null` can't be converted
+impl TryFrom<serde_json::Value> for Value {
+    type Error = Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        Ok(match value {
+            serde_json::Value::Null => {
+                return Err(Error::WrongValueType {
+                    expected: "number, boolean, string, map or array",
+                    got: "null",
+                });
+            }
+            serde_json::Value::Bool(v) => Value::Boolean(v),
+            serde_json::Value::Number(v) => match v.as_i64() {
+                Some(v) => Value::Integer(v),
+                None => Value::Number(v.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(v) => Value::String(v),
+            serde_json::Value::Array(v) => Value::Array(
+                v.into_iter()
+                    .map(Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            serde_json::Value::Object(v) => Value::Map(
+                v.into_iter()
+                    .map(|(key, value)| Ok((key, Value::try_from(value)?)))
+                    .collect::<Result<_, Self::Error>>()?,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::proto::{Error, OkResponse, Response, Value, ValueMap};
+
+    #[test]
+    fn should_round_trip_value_through_json() {
+        let source = include_str!("../../tests/corpus/ok_value_network_status.ttp");
+        let Response::Ok(OkResponse::WithValue(value)) = Response::parse_ttp(source).unwrap()
+        else {
+            panic!("Network status sample is not a value");
+        };
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn should_serialize_constant_apart_from_string() {
+        assert_eq!(
+            serde_json::to_string(&Value::Array(vec![
+                Value::Constant("DHCP".to_owned()),
+                Value::String("DHCP".to_owned()),
+                Value::Number(-10.5),
+                Value::Boolean(true)
+            ]))
+            .unwrap(),
+            "[{\"$constant\":\"DHCP\"},\"DHCP\",-10.5,true]"
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>("{\"$constant\":\"LINK_1_GB\"}").unwrap(),
+            Value::Constant("LINK_1_GB".to_owned())
+        );
+    }
+
+    #[test]
+    fn should_convert_to_json_value() {
+        let value = Value::Map(ValueMap::from([
+            (
+                "dnsStatus".to_owned(),
+                Value::Map(ValueMap::from([(
+                    "domainName".to_owned(),
+                    Value::String(String::new()),
+                )])),
+            ),
+            (
+                "levels".to_owned(),
+                Value::Array(vec![Value::Number(-10.0), Value::Boolean(true)]),
+            ),
+            (
+                "linkStatus".to_owned(),
+                Value::Constant("LINK_1_GB".to_owned()),
+            ),
+        ]));
+
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!({
+                "dnsStatus": {"domainName": ""},
+                "levels": [-10.0, true],
+                "linkStatus": "LINK_1_GB"
+            })
+        );
+    }
+
+    #[test]
+    fn should_convert_from_json_value() {
+        assert_eq!(
+            Value::try_from(json!({
+                "dnsStatus": {"domainName": ""},
+                "levels": [-10, 2.5],
+                "linkStatus": "LINK_1_GB"
+            }))
+            .unwrap(),
+            Value::Map(ValueMap::from([
+                (
+                    "dnsStatus".to_owned(),
+                    Value::Map(ValueMap::from([(
+                        "domainName".to_owned(),
+                        Value::String(String::new()),
+                    )])),
+                ),
+                (
+                    "levels".to_owned(),
+                    Value::Array(vec![Value::Integer(-10), Value::Number(2.5)]),
+                ),
+                (
+                    "linkStatus".to_owned(),
+                    Value::String("LINK_1_GB".to_owned())
+                ),
+            ]))
+        );
+        assert!(matches!(
+            Value::try_from(json!([1, null])),
+            Err(Error::WrongValueType { got: "null", .. })
+        ));
+    }
+}
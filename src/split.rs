@@ -0,0 +1,150 @@
+//! Independent halves of a session
+//!
+//! [TesiraSession::split] gives a [TesiraWriter] sending commands
+//! and a [TesiraReader] receiving publish tokens, so they can be moved to different threads.
+//!
+//! Command responses and publish tokens can no longer be correlated across the split:
+//! the writer does not wait for responses, and the reader skips `+OK` responses.
+//! This suits subscription consumers, use a [TesiraSession]
+//! when responses of commands are needed.
+
+use std::{
+    io::{self, BufWriter, Read, Write},
+    sync::mpsc::{self, Receiver},
+};
+
+use crate::{
+    Error, TesiraSession,
+    proto::{Command, PublishToken, Response, Value},
+};
+
+/// Sending half of a split session
+pub struct TesiraWriter<W: Write> {
+    write_stream: BufWriter<W>,
+}
+
+impl<W: Write> TesiraWriter<W> {
+    pub(crate) fn new(write_stream: BufWriter<W>) -> Self {
+        Self { write_stream }
+    }
+
+    /// Send a command without waiting for its response
+    pub fn send_command<'b>(&mut self, cmd: impl Into<Command<'b>>) -> Result<(), Error> {
+        cmd.into().write_ttp(&mut self.write_stream)?;
+        self.write_stream.flush()?;
+        Ok(())
+    }
+
+    /// Get back the write stream
+    pub fn into_inner(self) -> W {
+        // Every command is flushed once sent
+        self.write_stream.into_parts().0
+    }
+}
+
+/// Receiving half of a split session
+///
+/// Subscriptions registered before the split are kept.
+pub struct TesiraReader<R: Read> {
+    session: TesiraSession<R, io::Sink>,
+}
+
+impl<R: Read> TesiraReader<R> {
+    pub(crate) fn new(session: TesiraSession<R, io::Sink>) -> Self {
+        Self { session }
+    }
+
+    /// Register a subscription label and get a channel receiving its publish tokens
+    ///
+    /// The matching subscribe command is expected to be sent by the [TesiraWriter].
+    pub fn register_subscription(&mut self, label: impl Into<String>) -> Receiver<PublishToken> {
+        let (sender, receiver) = mpsc::channel();
+        self.session.subscriptions.insert(label.into(), sender);
+        receiver
+    }
+
+    /// Await for a publish token to come
+    ///
+    /// `+OK` responses are skipped, `-ERR` responses are returned as [Error::OperationFailed].
+    pub fn recv_token(&mut self) -> Result<PublishToken, Error> {
+        loop {
+            match self.session.recv_token() {
+                Err(Error::UnexpectedResponse(Response::Ok(_), _)) => continue,
+                Err(Error::UnexpectedResponse(Response::Err(e), _)) => {
+                    return Err(Error::OperationFailed(e));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Await for the next publish token and send it to the channel of its subscription
+    ///
+    /// See [TesiraSession::dispatch_next_token]
+    pub fn dispatch_next_token(&mut self) -> Result<(), Error> {
+        let token = self.recv_token()?;
+        self.session.dispatch_token(token)
+    }
+
+    /// Current value of a subscription, coalesced from every received publish token
+    ///
+    /// See [TesiraSession::current_value]
+    pub fn current_value(&self, label: &str) -> Option<&Value> {
+        self.session.current_value(label)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        Error, TesiraSession,
+        proto::{Command, PublishToken, Value},
+    };
+
+    #[test]
+    fn should_split_session() {
+        let input = "Welcome to the Tesira Text Protocol Server...\n\n+OK\n! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n+OK\n! \"publishToken\":\"MyMute\" \"value\":true\n-ERR address not found\n";
+        let mut session =
+            TesiraSession::new_from_stream(Cursor::new(input.as_bytes().to_vec()), Vec::new())
+                .unwrap();
+        let level = session
+            .subscribe(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+            .unwrap();
+
+        let (mut writer, mut reader) = session.split();
+        let mute = reader.register_subscription("MyMute");
+        writer
+            .send_command(Command::new_subscribe("Level3", "mute", [1], "MyMute"))
+            .unwrap();
+        assert_eq!(
+            writer.into_inner(),
+            "Level3 subscribe level 1 MyLevel\nLevel3 subscribe mute 1 MyMute\n".as_bytes()
+        );
+
+        reader.dispatch_next_token().unwrap();
+        reader.dispatch_next_token().unwrap();
+        assert_eq!(
+            level.try_recv().unwrap(),
+            PublishToken {
+                label: "MyLevel".to_owned(),
+                value: Value::Number(-10.0)
+            }
+        );
+        assert_eq!(
+            mute.try_recv().unwrap(),
+            PublishToken {
+                label: "MyMute".to_owned(),
+                value: Value::Boolean(true)
+            }
+        );
+        assert!(matches!(
+            reader.recv_token(),
+            Err(Error::OperationFailed(_))
+        ));
+        assert!(matches!(reader.recv_token(), Err(Error::UnexpectedEnd)));
+    }
+}
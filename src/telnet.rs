@@ -0,0 +1,187 @@
+//! Minimal Telnet support for devices exposing Tesira Text Protocol on port 23
+//!
+//! Devices open the connection with Telnet option negotiation before the welcome banner,
+//! every option is refused and negotiation bytes are removed from the read data.
+
+use std::io::{self, Read, Write};
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Data,
+    Command,
+    Option(u8),
+    Subnegotiation,
+    SubnegotiationCommand,
+}
+
+/// Stream removing Telnet commands from read data and refusing negotiated options
+pub struct TelnetStream<S: Read + Write> {
+    inner: S,
+    state: State,
+}
+
+impl<S: Read + Write> TelnetStream<S> {
+    /// Wrap a stream connected to a Telnet server
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            state: State::Data,
+        }
+    }
+
+    /// Get back the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Process a received byte, returning it if it's part of data
+    fn process(&mut self, byte: u8) -> io::Result<Option<u8>> {
+        let (state, data) = match (self.state, byte) {
+            (State::Data, IAC) => (State::Command, None),
+            (State::Data, byte) => (State::Data, Some(byte)),
+            (State::Command, IAC) => (State::Data, Some(IAC)), // Escaped 255 data byte
+            (State::Command, DO | DONT | WILL | WONT) => (State::Option(byte), None),
+            (State::Command, SB) => (State::Subnegotiation, None),
+            (State::Command, _) => (State::Data, None), // Other commands are ignored
+            (State::Option(command), option) => {
+                match command {
+                    DO => self.inner.write_all(&[IAC, WONT, option])?,
+                    WILL => self.inner.write_all(&[IAC, DONT, option])?,
+                    _ => {} // Option disabled, nothing to acknowledge
+                }
+                (State::Data, None)
+            }
+            (State::Subnegotiation, IAC) => (State::SubnegotiationCommand, None),
+            (State::Subnegotiation, _) => (State::Subnegotiation, None),
+            (State::SubnegotiationCommand, SE) => (State::Data, None),
+            (State::SubnegotiationCommand, _) => (State::Subnegotiation, None),
+        };
+        self.state = state;
+        Ok(data)
+    }
+}
+
+impl<S: Read + Write> Read for TelnetStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let byte_red = self.inner.read(buf)?;
+            if byte_red == 0 {
+                return Ok(0);
+            }
+
+            let mut data_len = 0;
+            for i in 0..byte_red {
+                if let Some(byte) = self.process(buf[i])? {
+                    buf[data_len] = byte;
+                    data_len += 1;
+                }
+            }
+            // Only Telnet commands were received, an empty read would mean end of stream
+            if data_len > 0 {
+                return Ok(data_len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{self, Cursor, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use pretty_assertions::assert_eq;
+
+    use super::{DO, DONT, IAC, SB, SE, TelnetStream, WILL, WONT};
+    use crate::TesiraSession;
+
+    struct MockServer {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for MockServer {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockServer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_refuse_negotiation_before_banner() {
+        let mut input = vec![
+            IAC, DO, 1, IAC, WILL, 3, IAC, SB, 24, 1, IAC, SE, IAC, DONT, 5,
+        ];
+        input.extend_from_slice("Welcome to the Tesira Text Protocol Server...\n".as_bytes());
+        input.extend_from_slice(&[IAC, IAC]);
+
+        let mut stream = TelnetStream::new(MockServer {
+            input: Cursor::new(input),
+            output: Vec::new(),
+        });
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).unwrap();
+
+        let mut expected_data = "Welcome to the Tesira Text Protocol Server...\n"
+            .as_bytes()
+            .to_vec();
+        expected_data.push(IAC);
+        assert_eq!(data, expected_data);
+        assert_eq!(stream.into_inner().output, vec![IAC, WONT, 1, IAC, DONT, 3]);
+    }
+
+    #[test]
+    fn should_open_session_after_negotiation() {
+        let mut input = vec![IAC, DO, 1, IAC, WILL, 3, IAC, SB, 24, 1, IAC, SE];
+        input.extend_from_slice("Welcome to the Tesira Text Protocol Server...\n\n".as_bytes());
+
+        let session = TesiraSession::new_from_stream(
+            TelnetStream::new(MockServer {
+                input: Cursor::new(input),
+                output: Vec::new(),
+            }),
+            Vec::new(),
+        );
+        assert!(session.is_ok());
+    }
+
+    #[test]
+    fn should_connect_with_host_and_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let device = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                stream
+                    .write_all("Welcome to the Tesira Text Protocol Server...\n\n".as_bytes())
+                    .unwrap();
+            }
+        });
+
+        assert!(TesiraSession::new_from_telnet("127.0.0.1", port).is_ok());
+        assert!(TesiraSession::new_from_telnet_host(&format!("127.0.0.1:{port}")).is_ok());
+        device.join().unwrap();
+    }
+}
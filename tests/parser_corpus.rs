@@ -0,0 +1,103 @@
+//! Golden-file tests of the response parser against real device output
+//!
+//! Each `tests/corpus/<name>.ttp` file contains one response line as sent by a device,
+//! its parsed form is compared to the snapshot stored in `tests/corpus/<name>.golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to (re)generate snapshots after an intended parser change.
+
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+use tesira_text_protocol::proto::{OkResponse, Response, Value};
+
+/// Deterministic representation of a [Value], map keys are sorted
+#[derive(Debug)]
+#[allow(dead_code)]
+enum ValueSnapshot {
+    Number(f64),
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    Map(BTreeMap<String, ValueSnapshot>),
+    Array(Vec<ValueSnapshot>),
+    Constant(String),
+}
+
+impl From<Value> for ValueSnapshot {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Number(v) => Self::Number(v),
+            Value::Integer(v) => Self::Integer(v),
+            Value::Boolean(v) => Self::Boolean(v),
+            Value::String(v) => Self::String(v),
+            Value::Map(v) => Self::Map(v.into_iter().map(|(k, v)| (k, v.into())).collect()),
+            Value::Array(v) => Self::Array(v.into_iter().map(Into::into).collect()),
+            Value::Constant(v) => Self::Constant(v),
+        }
+    }
+}
+
+/// Deterministic representation of a [Response]
+#[derive(Debug)]
+#[allow(dead_code)]
+enum ResponseSnapshot {
+    Ok,
+    WithValue(ValueSnapshot),
+    WithList(Vec<ValueSnapshot>),
+    Err { message: String },
+    PublishToken { label: String, value: ValueSnapshot },
+}
+
+impl From<Response> for ResponseSnapshot {
+    fn from(response: Response) -> Self {
+        match response {
+            Response::Ok(OkResponse::Ok) => Self::Ok,
+            Response::Ok(OkResponse::WithValue(v)) => Self::WithValue(v.into()),
+            Response::Ok(OkResponse::WithList(l)) => {
+                Self::WithList(l.into_iter().map(Into::into).collect())
+            }
+            Response::Err(e) => Self::Err { message: e.message },
+            Response::PublishToken(t) => Self::PublishToken {
+                label: t.label,
+                value: t.value.into(),
+            },
+        }
+    }
+}
+
+#[test]
+fn should_parse_corpus_as_golden_files() {
+    let corpus_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut samples = fs::read_dir(&corpus_dir)
+        .unwrap()
+        .map(|it| it.unwrap().path())
+        .filter(|it| it.extension().is_some_and(|ext| ext == "ttp"))
+        .collect::<Vec<_>>();
+    samples.sort();
+    assert!(!samples.is_empty(), "No sample found in {corpus_dir:?}");
+
+    let mut failures = Vec::new();
+    for sample in samples {
+        let source = fs::read_to_string(&sample).unwrap();
+        let snapshot = match Response::parse_ttp(&source) {
+            Ok(response) => format!("{:#?}\n", ResponseSnapshot::from(response)),
+            Err(e) => format!("Error: {e}\n"),
+        };
+
+        let golden = sample.with_extension("golden");
+        if update || !golden.exists() {
+            fs::write(&golden, &snapshot).unwrap();
+            if !update {
+                failures.push(format!("{golden:?} was missing and has been created"));
+            }
+            continue;
+        }
+
+        if fs::read_to_string(&golden).unwrap() != snapshot {
+            failures.push(format!("{sample:?} differs from golden file:\n{snapshot}"));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
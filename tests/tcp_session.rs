@@ -0,0 +1,159 @@
+//! End to end sessions over TCP, those tests do not rely on the `ssh` feature
+//! and must pass with `--no-default-features`
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use tesira_text_protocol::{
+    Error, TesiraSession,
+    proto::{Command, OkResponse, PublishToken, Value},
+};
+
+/// Start a device answering each received command line with scripted lines
+///
+/// Received command lines are echoed back before their answer, as devices do.
+fn spawn_device(answers: Vec<&'static str>) -> (TcpStream, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let device = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream
+            .write_all("Welcome to the Tesira Text Protocol Server...\n\n".as_bytes())
+            .unwrap();
+
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut received = Vec::new();
+        for answer in answers {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            stream.write_all(line.as_bytes()).unwrap();
+            stream.write_all(answer.as_bytes()).unwrap();
+            received.push(line.trim_end().to_owned());
+        }
+        // Keep connection open until session is dropped
+        while reader.read_line(&mut String::new()).unwrap_or(0) != 0 {}
+        received
+    });
+
+    (TcpStream::connect(address).unwrap(), device)
+}
+
+#[test]
+fn should_run_command_cycle_over_tcp() {
+    let (stream, device) = spawn_device(vec![
+        "+OK \"value\":-10.000000\n",
+        "+OK\n",
+        "-ERR address not found: {\"deviceId\":0 \"classCode\":0 \"instanceNum\":0}\n",
+    ]);
+    let mut session = TesiraSession::new_from_tcp(stream).unwrap();
+
+    assert_eq!(
+        session
+            .send_command(Command::new_get("Level3", "level", [2]))
+            .unwrap(),
+        OkResponse::WithValue(Value::Number(-10.0))
+    );
+    assert_eq!(
+        session
+            .send_command(Command::new_set("Level3", "mute", [2], true))
+            .unwrap(),
+        OkResponse::Ok
+    );
+    assert!(matches!(
+        session.send_command(Command::new_set("Level4", "mute", [2], true)),
+        Err(Error::CommandFailed { .. })
+    ));
+
+    drop(session);
+    assert_eq!(
+        device.join().unwrap(),
+        vec![
+            "Level3 get level 2",
+            "Level3 set mute 2 true",
+            "Level4 set mute 2 true"
+        ]
+    );
+}
+
+#[test]
+fn should_receive_publish_tokens_over_tcp() {
+    let (stream, device) = spawn_device(vec![
+        "+OK\n! \"publishToken\":\"MyLevel\" \"value\":-10.000000\n! \"publishToken\":\"MyLevel\" \"value\":-8.500000\n",
+    ]);
+    let mut session = TesiraSession::new_from_tcp(stream).unwrap();
+
+    session
+        .send_command(Command::new_subscribe("Level3", "level", [1], "MyLevel"))
+        .unwrap();
+    assert_eq!(
+        session.recv_token().unwrap(),
+        PublishToken {
+            label: "MyLevel".to_owned(),
+            value: Value::Number(-10.0)
+        }
+    );
+    assert_eq!(
+        session.recv_token().unwrap(),
+        PublishToken {
+            label: "MyLevel".to_owned(),
+            value: Value::Number(-8.5)
+        }
+    );
+
+    drop(session);
+    device.join().unwrap();
+}
+
+#[test]
+fn should_close_session_over_tcp() {
+    let (stream, device) = spawn_device(vec!["+OK\n"]);
+    let mut session = TesiraSession::new_from_tcp(stream).unwrap();
+
+    session
+        .send_command(Command::new_set("Level3", "mute", [2], true))
+        .unwrap();
+    session.close().unwrap();
+
+    // Device sees the end of stream
+    assert_eq!(device.join().unwrap(), vec!["Level3 set mute 2 true"]);
+}
+
+#[test]
+fn should_log_out_on_close() {
+    let (stream, device) = spawn_device(vec!["+OK\n", ""]);
+    let mut session = TesiraSession::new_from_tcp(stream).unwrap();
+
+    session
+        .send_command(Command::new_set("Level3", "mute", [2], true))
+        .unwrap();
+    session.close_with_logout().unwrap();
+
+    assert_eq!(
+        device.join().unwrap(),
+        vec!["Level3 set mute 2 true", "bye"]
+    );
+}
+
+#[test]
+fn should_timeout_over_tcp() {
+    let (stream, device) = spawn_device(vec![""]); // Never answers
+    let mut session = TesiraSession::new_from_tcp(stream).unwrap();
+    session
+        .set_command_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+
+    assert!(matches!(
+        session.send_command(Command::new_get("Level3", "level", [2])),
+        Err(Error::Timeout)
+    ));
+
+    drop(session);
+    device.join().unwrap();
+}